@@ -1,7 +1,8 @@
-use crate::{gen_rand_key, xorcrypt::XORCrypto};
+use crate::{buffer::PKCS7, gen_rand_key, xorcrypt::XORCrypto};
 use anyhow::{bail, ensure, Result};
 use openssl::symm::{Cipher as oCipher, Crypter, Mode};
 use rand::{distributions::Standard, prelude::Distribution};
+use std::marker::PhantomData;
 
 pub trait Cipher {
     const BLOCK_SIZE: usize = 16;
@@ -11,6 +12,7 @@ pub trait Cipher {
 
 pub struct AesEcb<const N: usize>;
 pub struct AesCbc<const N: usize>;
+pub struct AesCtr<const N: usize, L = LittleEndian64>(PhantomData<L>);
 
 pub type AesEcb128 = AesEcb<128>;
 pub type AesEcb256 = AesEcb<256>;
@@ -18,6 +20,41 @@ pub type AesEcb256 = AesEcb<256>;
 pub type AesCbc128 = AesCbc<128>;
 pub type AesCbc256 = AesCbc<256>;
 
+pub type AesCtr128 = AesCtr<128>;
+pub type AesCtr256 = AesCtr<256>;
+
+pub type AesCtrNist128 = AesCtr<128, BigEndian128>;
+pub type AesCtrNist256 = AesCtr<256, BigEndian128>;
+
+pub trait CounterLayout {
+    const NONCE_SIZE: usize;
+    fn counter_block(nonce: &[u8], counter: u64) -> Vec<u8>;
+}
+
+pub struct LittleEndian64;
+pub struct BigEndian128;
+
+impl CounterLayout for LittleEndian64 {
+    const NONCE_SIZE: usize = 8;
+
+    fn counter_block(nonce: &[u8], counter: u64) -> Vec<u8> {
+        nonce.iter().copied().chain(counter.to_le_bytes()).collect()
+    }
+}
+
+impl CounterLayout for BigEndian128 {
+    const NONCE_SIZE: usize = 16;
+
+    fn counter_block(nonce: &[u8], counter: u64) -> Vec<u8> {
+        let mut initial = [0_u8; 16];
+        initial.copy_from_slice(nonce);
+        u128::from_be_bytes(initial)
+            .wrapping_add(counter as u128)
+            .to_be_bytes()
+            .to_vec()
+    }
+}
+
 impl<const N: usize> Cipher for AesEcb<N> {
     fn encrypt(key: &[u8], _iv: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>> {
         ensure!(
@@ -34,6 +71,7 @@ impl<const N: usize> Cipher for AesEcb<N> {
         };
 
         let mut crypter = Crypter::new(cipher_engine, Mode::Encrypt, key, None)?;
+        crypter.pad(false);
 
         let res = data
             .chunks(Self::BLOCK_SIZE)
@@ -67,6 +105,7 @@ impl<const N: usize> Cipher for AesEcb<N> {
         };
 
         let mut crypter = Crypter::new(cipher_engine, Mode::Decrypt, key, None)?;
+        crypter.pad(false);
 
         let res = data
             .chunks(Self::BLOCK_SIZE)
@@ -75,7 +114,8 @@ impl<const N: usize> Cipher for AesEcb<N> {
                 ciphertext.resize_with(Self::BLOCK_SIZE, Default::default);
                 let mut plaintext = vec![0_u8; Self::BLOCK_SIZE * 2];
                 let size = crypter.update(&ciphertext, &mut plaintext)?;
-                Ok(plaintext[size..size + Self::BLOCK_SIZE].to_vec())
+                plaintext.truncate(size);
+                Ok(plaintext)
             })
             .collect::<Result<Vec<_>>>()?
             .into_iter()
@@ -102,6 +142,7 @@ impl<const N: usize> Cipher for AesCbc<N> {
         };
 
         let mut crypter = Crypter::new(cipher_engine, Mode::Encrypt, key, iv)?;
+        crypter.pad(false);
         let mut iv = iv.unwrap().to_vec();
 
         let res = data
@@ -139,6 +180,7 @@ impl<const N: usize> Cipher for AesCbc<N> {
         };
 
         let mut crypter = Crypter::new(cipher_engine, Mode::Decrypt, key, iv)?;
+        crypter.pad(false);
         let iv = iv.unwrap();
 
         let res = iv
@@ -153,7 +195,7 @@ impl<const N: usize> Cipher for AesCbc<N> {
                 ciphertext.resize_with(Self::BLOCK_SIZE, Default::default);
                 let mut interim = vec![0_u8; Self::BLOCK_SIZE * 2];
                 let size = crypter.update(&ciphertext, &mut interim)?;
-                let plaintext = iv.as_slice().xor(&interim[size..size + Self::BLOCK_SIZE])?;
+                let plaintext = iv.as_slice().xor(&interim[..size])?;
                 Ok(plaintext)
             })
             .collect::<Result<Vec<_>>>()?
@@ -164,6 +206,65 @@ impl<const N: usize> Cipher for AesCbc<N> {
     }
 }
 
+impl<const N: usize, L: CounterLayout> AesCtr<N, L> {
+    pub fn keystream(key: &[u8], nonce: &[u8], first_block: u64, len: usize) -> Result<Vec<u8>> {
+        ensure!(
+            nonce.len() == L::NONCE_SIZE,
+            "Unexpected nonce length {} for CTR mode, expected {}",
+            nonce.len(),
+            L::NONCE_SIZE
+        );
+
+        let blocks = len.div_ceil(Self::BLOCK_SIZE) as u64;
+        let counters = (first_block..first_block + blocks)
+            .flat_map(|counter| L::counter_block(nonce, counter))
+            .collect::<Vec<u8>>();
+        let mut keystream = AesEcb::<N>::encrypt(key, None, &counters)?;
+        keystream.truncate(len);
+        Ok(keystream)
+    }
+
+    /// Overwrites the plaintext at `offset` with `newtext`, growing
+    /// `ciphertext` if the edit runs past its end.
+    pub fn edit(
+        ciphertext: &mut Vec<u8>,
+        key: &[u8],
+        nonce: &[u8],
+        offset: usize,
+        newtext: &[u8],
+    ) -> Result<()> {
+        ensure!(
+            offset <= ciphertext.len(),
+            "Offset {} is past the end of the ciphertext",
+            offset
+        );
+
+        let skip = offset % Self::BLOCK_SIZE;
+        let first_block = (offset / Self::BLOCK_SIZE) as u64;
+        let keystream = Self::keystream(key, nonce, first_block, skip + newtext.len())?;
+        let patch = newtext.xor(&keystream[skip..])?;
+
+        let end = offset + patch.len();
+        if end > ciphertext.len() {
+            ciphertext.resize(end, 0);
+        }
+        ciphertext[offset..end].copy_from_slice(&patch);
+        Ok(())
+    }
+}
+
+impl<const N: usize, L: CounterLayout> Cipher for AesCtr<N, L> {
+    fn encrypt(key: &[u8], iv: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>> {
+        ensure!(iv.is_some(), "Nonce is required for this cipher");
+        let keystream = Self::keystream(key, iv.unwrap(), 0, data.len())?;
+        data.xor(&keystream)
+    }
+
+    fn decrypt(key: &[u8], iv: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>> {
+        Self::encrypt(key, iv, data)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum AesMode {
     ECB,
@@ -184,6 +285,9 @@ pub struct OracleBuilder<'a> {
     key: Option<&'a [u8]>,
     key_len: Option<usize>,
     iv: Option<&'a [u8]>,
+    prefix: Option<&'a [u8]>,
+    prefix_len: Option<usize>,
+    suffix: Option<&'a [u8]>,
 }
 
 impl<'a> OracleBuilder<'a> {
@@ -207,6 +311,21 @@ impl<'a> OracleBuilder<'a> {
         self
     }
 
+    pub fn with_prefix(mut self, prefix: &'a [u8]) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    pub fn with_prefix_len(mut self, prefix_len: usize) -> Self {
+        self.prefix_len = Some(prefix_len);
+        self
+    }
+
+    pub fn with_suffix(mut self, suffix: &'a [u8]) -> Self {
+        self.suffix = Some(suffix);
+        self
+    }
+
     pub fn build(self) -> Oracle {
         let mode = self.mode.unwrap_or_else(rand::random);
         let key_len = self.key_len.unwrap_or(16);
@@ -219,7 +338,18 @@ impl<'a> OracleBuilder<'a> {
             (AesMode::CBC, None) => Some(gen_rand_key(AesCbc128::BLOCK_SIZE)),
             (AesMode::CBC, Some(iv)) => Some(iv.to_vec()),
         };
-        Oracle { mode, key, iv }
+        let prefix = self
+            .prefix
+            .map(|slice| slice.to_vec())
+            .unwrap_or_else(|| gen_rand_key(self.prefix_len.unwrap_or(0)));
+        let suffix = self.suffix.map(|slice| slice.to_vec()).unwrap_or_default();
+        Oracle {
+            mode,
+            key,
+            iv,
+            prefix,
+            suffix,
+        }
     }
 }
 
@@ -227,6 +357,8 @@ pub struct Oracle {
     mode: AesMode,
     key: Vec<u8>,
     iv: Option<Vec<u8>>,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
 }
 
 impl Oracle {
@@ -236,6 +368,9 @@ impl Oracle {
             key: None,
             key_len: None,
             iv: None,
+            prefix: None,
+            prefix_len: None,
+            suffix: None,
         }
     }
 
@@ -249,9 +384,17 @@ impl Oracle {
 
     pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
         let iv = self.iv.as_deref();
+        let data = self
+            .prefix
+            .iter()
+            .chain(data.iter())
+            .chain(self.suffix.iter())
+            .copied()
+            .collect::<Vec<u8>>()
+            .pad(AesEcb128::BLOCK_SIZE as u8);
         match self.mode {
-            AesMode::ECB => AesEcb128::encrypt(&self.key, iv, data),
-            AesMode::CBC => AesCbc128::encrypt(&self.key, iv, data),
+            AesMode::ECB => AesEcb128::encrypt(&self.key, iv, &data),
+            AesMode::CBC => AesCbc128::encrypt(&self.key, iv, &data),
         }
     }
 }
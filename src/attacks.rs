@@ -0,0 +1,803 @@
+use crate::aes::{AesCbc128, AesEcb128, Cipher};
+use crate::buffer::{BufferOps, PKCS7};
+use crate::clock::Clock;
+use crate::dh::modexp;
+use crate::dh::protocol::{decrypt_message, Direction, Message, Middle};
+use crate::gen_rand_key;
+use crate::gen_rand_key_with;
+use crate::hash::MerkleDamgard;
+use crate::mt19937::{Mt19937, Mt19937Cipher};
+use crate::services::{profile_layout, KeyAsIvError, COMMENT_PREFIX};
+use crate::srp::{session_proof, Channel, SrpMessage, SrpParams};
+use crate::transpose;
+use crate::xorcrypt::XORCrypto;
+use anyhow::{anyhow, bail, ensure, Result};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ops::{Range, RangeInclusive};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const MAX_BLOCK_SIZE: usize = 64;
+const FILLER: u8 = b'A';
+const MARKERS: [u8; 2] = [b'X', b'Y'];
+const TIMING_FINALISTS: usize = 4;
+
+pub fn has_repeated_blocks(data: &[u8], block_size: usize) -> bool {
+    let chunks = data.chunks(block_size);
+    chunks.len() != chunks.collect::<HashSet<_>>().len()
+}
+
+/// Feeds the oracle growing inputs until its output grows by a whole block.
+/// Returns the block size and the number of bytes the oracle adds around
+/// attacker input, assuming PKCS#7 padding.
+pub fn detect_block_size<F>(oracle: &F) -> Result<(usize, usize)>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let initial_len = oracle(&[])?.len();
+    for fill in 1..=MAX_BLOCK_SIZE {
+        let len = oracle(&vec![FILLER; fill])?.len();
+        if len > initial_len {
+            return Ok((len - initial_len, initial_len - fill));
+        }
+    }
+    bail!("Block size exceeds {} bytes", MAX_BLOCK_SIZE)
+}
+
+/// Finds the length of whatever an ECB oracle prepends to attacker input by
+/// padding two identical marker blocks until they line up with a block
+/// boundary. The alignment has to hold for two different markers, so prefix
+/// or suffix bytes that happen to match a marker can't fake a boundary.
+pub fn locate_prefix<F>(oracle: &F, block_size: usize) -> Result<usize>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    for pad in 0..block_size {
+        let duplicates = MARKERS
+            .iter()
+            .map(|&marker| {
+                let mut input = vec![marker.wrapping_add(1); pad];
+                input.extend(std::iter::repeat_n(marker, 2 * block_size));
+                let ciphertext = oracle(&input)?;
+                let blocks = ciphertext.chunks(block_size).collect::<Vec<_>>();
+                Ok(blocks.windows(2).position(|pair| pair[0] == pair[1]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(block) = duplicates[0] {
+            if duplicates.iter().all(|&dup| dup == Some(block)) {
+                return Ok(block * block_size - pad);
+            }
+        }
+    }
+    bail!("Oracle is not using ECB mode")
+}
+
+/// Recovers `secret_len` bytes an ECB oracle appends directly after
+/// attacker input, one byte at a time.
+pub fn ecb_recover_suffix<F>(oracle: &F, block_size: usize, secret_len: usize) -> Result<Vec<u8>>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let mut recovered = vec![FILLER; block_size - 1];
+    for i in 0..secret_len {
+        let fill = block_size - 1 - i % block_size;
+        let block = i / block_size;
+        let ciphertext = oracle(&recovered[..fill])?;
+        let target = &ciphertext[block * block_size..(block + 1) * block_size];
+
+        let window = &recovered[recovered.len() - (block_size - 1)..];
+        let candidates = (0..=u8::MAX)
+            .flat_map(|byte| window.iter().copied().chain(std::iter::once(byte)))
+            .collect::<Vec<u8>>();
+        let dictionary = oracle(&candidates)?;
+
+        match dictionary
+            .chunks(block_size)
+            .take(256)
+            .position(|block| block == target)
+        {
+            Some(byte) => recovered.push(byte as u8),
+            None => bail!("Failed to recover secret byte at position {}", i),
+        }
+    }
+
+    Ok(recovered.split_off(block_size - 1))
+}
+
+/// Recovers the secret an ECB oracle appends to attacker controlled input,
+/// skipping over any fixed prefix the oracle places before it.
+pub fn ecb_byte_at_a_time<F>(oracle: F) -> Result<Vec<u8>>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let (block_size, extra_len) = detect_block_size(&oracle)?;
+    let prefix_len = locate_prefix(&oracle, block_size)?;
+    let align = (block_size - prefix_len % block_size) % block_size;
+
+    let aligned = |input: &[u8]| {
+        let mut data = vec![FILLER; align];
+        data.extend_from_slice(input);
+        Ok(oracle(&data)?.split_off(prefix_len + align))
+    };
+
+    ecb_recover_suffix(&aligned, block_size, extra_len - prefix_len)
+}
+
+/// Prepends filler to `chosen` so that it starts on a block boundary once the
+/// oracle has put `offset` bytes in front of it. Returns the input to submit
+/// and the index of the block where `chosen` begins.
+pub fn block_aligned<C: Cipher>(offset: usize, chosen: &[u8]) -> (Vec<u8>, usize) {
+    let fill = (C::BLOCK_SIZE - offset % C::BLOCK_SIZE) % C::BLOCK_SIZE;
+    let mut input = vec![FILLER; fill];
+    input.extend_from_slice(chosen);
+    (input, (offset + fill) / C::BLOCK_SIZE)
+}
+
+/// Joins ranges of whole blocks taken from several ciphertexts.
+pub fn splice_blocks<C: Cipher>(pieces: &[(&[u8], Range<usize>)]) -> Result<Vec<u8>> {
+    pieces
+        .iter()
+        .map(|(ciphertext, blocks)| {
+            let bytes = blocks.start * C::BLOCK_SIZE..blocks.end * C::BLOCK_SIZE;
+            ensure!(
+                bytes.end <= ciphertext.len(),
+                "Block range {:?} exceeds ciphertext length {}",
+                blocks,
+                ciphertext.len()
+            );
+            Ok(&ciphertext[bytes])
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(|slices| slices.concat())
+}
+
+/// Forges an ECB ciphertext whose plaintext ends in `payload` right after
+/// the `trailer_len` bytes the oracle appends to attacker input. `offset` is
+/// the number of bytes the oracle places before attacker input.
+pub fn ecb_cut_and_paste<C, F>(
+    oracle: F,
+    offset: usize,
+    trailer_len: usize,
+    payload: &[u8],
+) -> Result<Vec<u8>>
+where
+    C: Cipher,
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let payload = payload.to_vec().pad(C::BLOCK_SIZE as u8);
+    let (input, start) = block_aligned::<C>(offset, &payload);
+    let tail = oracle(&input)?;
+
+    let (input, end) = block_aligned::<C>(offset + trailer_len, &[]);
+    let head = oracle(&input)?;
+
+    splice_blocks::<C>(&[
+        (&head, 0..end),
+        (&tail, start..start + payload.len() / C::BLOCK_SIZE),
+    ])
+}
+
+pub fn forge_admin_profile<F>(profile_for: F) -> Result<Vec<u8>>
+where
+    F: Fn(&str) -> Result<Vec<u8>>,
+{
+    let oracle = |input: &[u8]| profile_for(std::str::from_utf8(input)?);
+    let (offset, trailer_len) = profile_layout();
+    ecb_cut_and_paste::<AesEcb128, _>(oracle, offset, trailer_len, b"admin")
+}
+
+/// Flips bits in block `block_index - 1` of a CBC ciphertext so that the
+/// start of plaintext block `block_index` changes from `known` to `desired`.
+/// The previous plaintext block is garbled in the process.
+pub fn cbc_bitflip<C: Cipher>(
+    ciphertext: &[u8],
+    block_index: usize,
+    known: &[u8],
+    desired: &[u8],
+) -> Result<Vec<u8>> {
+    ensure!(
+        block_index > 0,
+        "The first block can only be flipped through the IV"
+    );
+    ensure!(
+        known.len() <= C::BLOCK_SIZE,
+        "Can't flip more than {} bytes at a time",
+        C::BLOCK_SIZE
+    );
+    ensure!(
+        (block_index + 1) * C::BLOCK_SIZE <= ciphertext.len(),
+        "Block {} is out of range",
+        block_index
+    );
+
+    xor_patch(
+        ciphertext,
+        (block_index - 1) * C::BLOCK_SIZE,
+        known,
+        desired,
+    )
+}
+
+pub fn forge_admin_comment_cbc<F>(encrypt_userdata: F) -> Result<Vec<u8>>
+where
+    F: Fn(&str) -> Result<Vec<u8>>,
+{
+    let payload = b";admin=true;";
+    let scratch = vec![FILLER; 2 * AesCbc128::BLOCK_SIZE];
+    let (input, start) = block_aligned::<AesCbc128>(COMMENT_PREFIX.len(), &scratch);
+    let ciphertext = encrypt_userdata(std::str::from_utf8(&input)?)?;
+    cbc_bitflip::<AesCbc128>(&ciphertext, start + 1, &scratch[..payload.len()], payload)
+}
+
+/// Flips bits of a stream cipher ciphertext so that the plaintext at
+/// `offset` changes from `known` to `desired`. Nothing else is disturbed.
+pub fn ctr_bitflip(
+    ciphertext: &[u8],
+    offset: usize,
+    known: &[u8],
+    desired: &[u8],
+) -> Result<Vec<u8>> {
+    xor_patch(ciphertext, offset, known, desired)
+}
+
+/// XORs `known ^ wanted` into a copy of `ciphertext` at `offset`.
+fn xor_patch(ciphertext: &[u8], offset: usize, known: &[u8], wanted: &[u8]) -> Result<Vec<u8>> {
+    ensure!(
+        offset + known.len() <= ciphertext.len(),
+        "Patched range runs past the end of the ciphertext"
+    );
+    let delta = known.xor(wanted)?;
+    let patch = ciphertext[offset..offset + delta.len()].xor(&delta)?;
+    let mut patched = ciphertext.to_vec();
+    patched[offset..offset + delta.len()].copy_from_slice(&patch);
+    Ok(patched)
+}
+
+pub fn forge_admin_comment_ctr<F>(encrypt_userdata: F) -> Result<Vec<u8>>
+where
+    F: Fn(&str) -> Result<Vec<u8>>,
+{
+    let payload = b";admin=true;";
+    let scratch = vec![FILLER; payload.len()];
+    let ciphertext = encrypt_userdata(std::str::from_utf8(&scratch)?)?;
+    ctr_bitflip(&ciphertext, COMMENT_PREFIX.len(), &scratch, payload)
+}
+
+pub trait PaddingOracle {
+    fn check_padding(&self, iv: &[u8], ciphertext: &[u8]) -> Result<bool>;
+}
+
+impl<F> PaddingOracle for F
+where
+    F: Fn(&[u8], &[u8]) -> Result<bool>,
+{
+    fn check_padding(&self, iv: &[u8], ciphertext: &[u8]) -> Result<bool> {
+        self(iv, ciphertext)
+    }
+}
+
+/// Recovers the intermediate state of a single CBC block, i.e. the block
+/// cipher's decryption of `block` before it is XORed with the previous one.
+fn padding_oracle_block<C, O>(oracle: &O, block: &[u8]) -> Result<Vec<u8>>
+where
+    C: Cipher,
+    O: PaddingOracle + ?Sized,
+{
+    let mut intermediate = vec![0_u8; C::BLOCK_SIZE];
+    let mut forged_iv = vec![0_u8; C::BLOCK_SIZE];
+
+    for padding in 1..=C::BLOCK_SIZE {
+        let pos = C::BLOCK_SIZE - padding;
+        for i in pos + 1..C::BLOCK_SIZE {
+            forged_iv[i] = intermediate[i] ^ padding as u8;
+        }
+
+        let mut found = None;
+        for guess in 0..=u8::MAX {
+            forged_iv[pos] = guess;
+            if !oracle.check_padding(&forged_iv, block)? {
+                continue;
+            }
+            if padding == 1 && pos > 0 {
+                // Rule out the plaintext accidentally ending in e.g. 0x02 0x02
+                let mut probe = forged_iv.clone();
+                probe[pos - 1] ^= 0xff;
+                if !oracle.check_padding(&probe, block)? {
+                    continue;
+                }
+            }
+            found = Some(guess);
+            break;
+        }
+
+        match found {
+            Some(guess) => intermediate[pos] = guess ^ padding as u8,
+            None => bail!("No valid padding found for byte {} of block", pos),
+        }
+    }
+
+    Ok(intermediate)
+}
+
+/// Decrypts a CBC ciphertext using nothing but an oracle that reports
+/// whether the padding of a decrypted message is valid. `progress` is
+/// called after every block with its index, the total block count and the
+/// recovered plaintext of that block.
+pub fn padding_oracle_attack<C, O, P>(
+    oracle: &O,
+    iv: &[u8],
+    ciphertext: &[u8],
+    mut progress: P,
+) -> Result<Vec<u8>>
+where
+    C: Cipher,
+    O: PaddingOracle + ?Sized,
+    P: FnMut(usize, usize, &[u8]),
+{
+    ensure!(
+        iv.len() == C::BLOCK_SIZE && ciphertext.len().is_multiple_of(C::BLOCK_SIZE),
+        "Ciphertext isn't made of whole blocks"
+    );
+
+    let blocks = std::iter::once(iv)
+        .chain(ciphertext.chunks(C::BLOCK_SIZE))
+        .collect::<Vec<_>>();
+    let total = blocks.len() - 1;
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for (i, pair) in blocks.windows(2).enumerate() {
+        let intermediate = padding_oracle_block::<C, O>(oracle, pair[1])?;
+        let block = pair[0].xor(&intermediate)?;
+        progress(i, total, &block);
+        plaintext.extend(block);
+    }
+
+    Ok(plaintext.try_strip(C::BLOCK_SIZE as u8)?)
+}
+
+/// Recovers the keystream shared by ciphertexts encrypted under a reused CTR
+/// nonce, treating each column of the truncated ciphertexts as single-byte
+/// XOR. Only as many keystream bytes as the shortest ciphertext are found.
+pub fn break_fixed_nonce_ctr<T: AsRef<[u8]>>(ciphertexts: &[T]) -> Result<Vec<u8>> {
+    let min_len = ciphertexts.iter().map(|c| c.as_ref().len()).min();
+    ensure!(
+        min_len.is_some_and(|len| len > 0),
+        "Need at least one non-empty ciphertext"
+    );
+    let rows = ciphertexts
+        .iter()
+        .map(|c| &c.as_ref()[..min_len.unwrap()])
+        .collect::<Vec<_>>();
+
+    transpose(&rows)
+        .iter()
+        .map(|column| guess_keystream_byte(column))
+        .collect()
+}
+
+/// Like `break_fixed_nonce_ctr`, but keeps going to the end of the longest
+/// ciphertext. Every keystream byte comes with a confidence between 0 and 1,
+/// the share of ciphertexts that were long enough to contribute to it.
+pub fn break_fixed_nonce_ctr_untruncated<T: AsRef<[u8]>>(
+    ciphertexts: &[T],
+) -> Result<Vec<(u8, f32)>> {
+    let max_len = ciphertexts.iter().map(|c| c.as_ref().len()).max();
+    ensure!(
+        max_len.is_some_and(|len| len > 0),
+        "Need at least one non-empty ciphertext"
+    );
+
+    (0..max_len.unwrap())
+        .map(|i| {
+            let column = ciphertexts
+                .iter()
+                .filter_map(|c| c.as_ref().get(i).copied())
+                .collect::<Vec<u8>>();
+            let confidence = column.len() as f32 / ciphertexts.len() as f32;
+            Ok((guess_keystream_byte(&column)?, confidence))
+        })
+        .collect()
+}
+
+/// Columns mix the capital starting a line with lowercase text, and the
+/// frequency table only knows lowercase, so plaintext is scored with its
+/// case folded.
+fn guess_keystream_byte(column: &[u8]) -> Result<u8> {
+    column
+        .guess_xor_byte(|text| text.to_ascii_lowercase().freq_rank())
+        .map(|(key, _)| key)
+}
+
+/// Finds the seed within `window` that makes MT19937 produce `output` as its
+/// first value. The newest candidates are tried first.
+pub fn crack_mt19937_seed(output: u32, window: RangeInclusive<u32>) -> Option<u32> {
+    window
+        .rev()
+        .find(|&seed| Mt19937::new(seed).next_u32() == output)
+}
+
+/// Recovers the 16 bit seed of an `Mt19937Cipher` ciphertext whose plaintext
+/// is known to end in `known_suffix`.
+pub fn crack_mt19937_cipher_seed(ciphertext: &[u8], known_suffix: &[u8]) -> Option<u16> {
+    if known_suffix.len() > ciphertext.len() {
+        return None;
+    }
+    let offset = ciphertext.len() - known_suffix.len();
+    (0..=u16::MAX).find(|seed| {
+        Mt19937Cipher::keystream(&seed.to_le_bytes(), ciphertext.len()).is_ok_and(|keystream| {
+            keystream[offset..]
+                .iter()
+                .zip(&ciphertext[offset..])
+                .zip(known_suffix)
+                .all(|((k, c), p)| k ^ c == *p)
+        })
+    })
+}
+
+/// Checks whether `token` is what an MT19937 seeded with the current time,
+/// give or take `tolerance` seconds, would have produced.
+pub fn is_time_seeded_token<C: Clock>(token: &[u8], clock: &C, tolerance: u32) -> bool {
+    let now = clock.now() as u32;
+    (now.saturating_sub(tolerance)..=now.saturating_add(tolerance))
+        .any(|seed| gen_rand_key_with(&mut Mt19937::new(seed), token.len()) == token)
+}
+
+/// Recovers CTR plaintext from an exposed edit function by overwriting the
+/// whole message with zeroes, which turns the edited ciphertext into the
+/// keystream. `edit` returns the ciphertext after the edit, and the
+/// recovered plaintext is written back before returning.
+pub fn ctr_edit_recover<F>(ciphertext: &[u8], mut edit: F) -> Result<Vec<u8>>
+where
+    F: FnMut(usize, &[u8]) -> Result<Vec<u8>>,
+{
+    let keystream = edit(0, &vec![0; ciphertext.len()])?;
+    let plaintext = ciphertext.xor(&keystream)?;
+    ensure!(
+        edit(0, &plaintext)? == ciphertext,
+        "Restoring the plaintext didn't reproduce the original ciphertext"
+    );
+    Ok(plaintext)
+}
+
+/// Recovers the key of a CBC receiver that uses the key as its IV and leaks
+/// the decrypted plaintext when it isn't valid ASCII. Needs a ciphertext of
+/// at least three blocks.
+pub fn recover_key_as_iv<D>(ciphertext: &[u8], decrypt: D) -> Result<Vec<u8>>
+where
+    D: Fn(&[u8]) -> Result<Vec<u8>, KeyAsIvError>,
+{
+    const BS: usize = AesCbc128::BLOCK_SIZE;
+    ensure!(
+        ciphertext.len() >= 3 * BS,
+        "Need at least 3 blocks of ciphertext"
+    );
+
+    let forged = [
+        &ciphertext[..BS],
+        &[0; BS][..],
+        &ciphertext[..BS],
+        &ciphertext[3 * BS..],
+    ]
+    .concat();
+
+    match decrypt(&forged) {
+        Err(KeyAsIvError::HighAscii(plaintext)) => plaintext[..BS].xor(&plaintext[2 * BS..3 * BS]),
+        Err(e) => bail!("Receiver didn't leak the plaintext: {}", e),
+        Ok(_) => bail!("Receiver accepted the forged ciphertext"),
+    }
+}
+
+/// Forges a secret-prefix MAC for `message || glue || suffix` out of a known
+/// tag for `message`, trying every key length in `key_lens` against
+/// `verify`. Returns the forged message and its tag.
+pub fn length_extension<H, V>(
+    message: &[u8],
+    tag: &[u8],
+    suffix: &[u8],
+    key_lens: Range<usize>,
+    verify: V,
+) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    H: MerkleDamgard,
+    V: Fn(&[u8], &[u8]) -> bool,
+{
+    for key_len in key_lens {
+        let original_len = (key_len + message.len()) as u64;
+        let glue = H::padding(original_len);
+
+        let mut hasher = H::from_digest(tag, original_len + glue.len() as u64)?;
+        hasher.update(suffix);
+        let forged_tag = hasher.finalize();
+        let forged_message = [message, &glue, suffix].concat();
+
+        if verify(&forged_message, &forged_tag) {
+            return Ok((forged_message, forged_tag));
+        }
+    }
+    bail!("No key length in range produced a valid forgery")
+}
+
+/// Recovers a `len` byte signature from a verifier that compares it one byte
+/// at a time and exits early. Each candidate byte is timed `samples` times
+/// and scored by its fastest response, which filters out scheduling noise.
+/// The few slowest candidates are then timed again before picking one, so a
+/// wrong byte that was unlucky every time doesn't win.
+pub fn timing_attack<F>(len: usize, samples: usize, mut check: F) -> Result<Vec<u8>>
+where
+    F: FnMut(&[u8]) -> Result<bool>,
+{
+    let samples = samples.max(1);
+    let mut signature = vec![0_u8; len];
+    for i in 0..len {
+        let mut fastest = [Duration::MAX; 256];
+        for byte in 0..=u8::MAX {
+            let timing = &mut fastest[byte as usize];
+            if time_candidate(&mut check, &mut signature, i, byte, samples, timing)? {
+                return Ok(signature);
+            }
+        }
+
+        let mut finalists = (0..=u8::MAX).collect::<Vec<_>>();
+        finalists.sort_by_key(|&byte| Reverse(fastest[byte as usize]));
+        finalists.truncate(TIMING_FINALISTS);
+        for &byte in &finalists {
+            let timing = &mut fastest[byte as usize];
+            let rounds = samples * TIMING_FINALISTS;
+            if time_candidate(&mut check, &mut signature, i, byte, rounds, timing)? {
+                return Ok(signature);
+            }
+        }
+        signature[i] = finalists
+            .into_iter()
+            .max_by_key(|&byte| fastest[byte as usize])
+            .unwrap_or_default();
+    }
+    Ok(signature)
+}
+
+fn time_candidate<F>(
+    check: &mut F,
+    signature: &mut [u8],
+    index: usize,
+    byte: u8,
+    rounds: usize,
+    fastest: &mut Duration,
+) -> Result<bool>
+where
+    F: FnMut(&[u8]) -> Result<bool>,
+{
+    signature[index] = byte;
+    for _ in 0..rounds {
+        let start = Instant::now();
+        if check(signature)? {
+            return Ok(true);
+        }
+        *fastest = (*fastest).min(start.elapsed());
+    }
+    Ok(false)
+}
+
+/// Challenge 34: swaps both public keys for p, so each side computes
+/// p^x mod p = 0 as its shared secret, and reads everything in flight.
+#[derive(Default)]
+pub struct DhKeyFixing {
+    p: Option<BigUint>,
+    pub intercepted: Vec<(Direction, Vec<u8>)>,
+}
+
+impl Middle for DhKeyFixing {
+    fn relay(&mut self, direction: Direction, message: Message) -> Result<Message> {
+        match message {
+            Message::KeyExchange { p, g, .. } => {
+                self.p = Some(p.clone());
+                Ok(Message::KeyExchange {
+                    public: p.clone(),
+                    p,
+                    g,
+                })
+            }
+            Message::PublicKey(_) => {
+                let p = self.p.clone();
+                let p = p.ok_or_else(|| anyhow!("Public key relayed before the key exchange"))?;
+                Ok(Message::PublicKey(p))
+            }
+            Message::Encrypted { .. } => {
+                let plaintext = decrypt_message(&BigUint::zero(), &message)?;
+                self.intercepted.push((direction, plaintext));
+                Ok(message)
+            }
+            Message::Negotiate { ref p, .. } => {
+                self.p = Some(p.clone());
+                Ok(message)
+            }
+            Message::Ack { .. } => Ok(message),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaliciousG {
+    One,
+    P,
+    PMinusOne,
+}
+
+/// Challenge 35: rewrites g during group negotiation so the shared secret
+/// collapses to a value the attacker can predict, then reads everything in
+/// flight.
+pub struct DhMaliciousG {
+    g: MaliciousG,
+    p: Option<BigUint>,
+    public_keys: Vec<BigUint>,
+    pub intercepted: Vec<(Direction, Vec<u8>)>,
+}
+
+impl DhMaliciousG {
+    pub fn new(g: MaliciousG) -> Self {
+        DhMaliciousG {
+            g,
+            p: None,
+            public_keys: Vec::new(),
+            intercepted: Vec::new(),
+        }
+    }
+
+    fn malicious_g(&self, p: &BigUint) -> BigUint {
+        match self.g {
+            MaliciousG::One => BigUint::one(),
+            MaliciousG::P => p.clone(),
+            MaliciousG::PMinusOne => p - 1_u32,
+        }
+    }
+
+    /// With g = p - 1 both public keys and the secret are either 1 or p - 1.
+    /// The secret is p - 1 only when both private keys are odd, which shows
+    /// up as both public keys being p - 1.
+    fn predicted_secret(&self) -> Result<BigUint> {
+        let p = self
+            .p
+            .as_ref()
+            .ok_or_else(|| anyhow!("Encrypted message relayed before negotiation"))?;
+        Ok(match self.g {
+            MaliciousG::One => BigUint::one(),
+            MaliciousG::P => BigUint::zero(),
+            MaliciousG::PMinusOne => {
+                let p_minus_one = p - 1_u32;
+                match self.public_keys.as_slice() {
+                    [a, b] if *a == p_minus_one && *b == p_minus_one => p_minus_one,
+                    _ => BigUint::one(),
+                }
+            }
+        })
+    }
+}
+
+impl Middle for DhMaliciousG {
+    fn relay(&mut self, direction: Direction, message: Message) -> Result<Message> {
+        match message {
+            Message::Negotiate { p, .. } => {
+                let g = self.malicious_g(&p);
+                self.p = Some(p.clone());
+                Ok(Message::Negotiate { p, g })
+            }
+            Message::Ack { p, .. } => {
+                let g = self.malicious_g(&p);
+                Ok(Message::Ack { p, g })
+            }
+            Message::PublicKey(ref public) => {
+                self.public_keys.push(public.clone());
+                Ok(message)
+            }
+            Message::Encrypted { .. } => {
+                let plaintext = decrypt_message(&self.predicted_secret()?, &message)?;
+                self.intercepted.push((direction, plaintext));
+                Ok(message)
+            }
+            Message::KeyExchange { .. } => {
+                bail!("Malicious g needs a negotiated group, got a bare key exchange")
+            }
+        }
+    }
+}
+
+/// Challenge 37: logs in as `identity` without its password by sending a
+/// multiple of N as the public key, which pins the server's S to 0.
+pub fn srp_zero_key_login(
+    channel: &Channel,
+    params: &SrpParams,
+    identity: &str,
+    multiple: u32,
+) -> Result<bool> {
+    channel.send(SrpMessage::Hello {
+        identity: identity.to_string(),
+        public: &params.n * multiple,
+    })?;
+    let salt = match channel.recv()? {
+        SrpMessage::Challenge { salt, .. } => salt,
+        other => bail!("Expected a challenge, got {:?}", other),
+    };
+    channel.send(SrpMessage::Proof(session_proof(&BigUint::zero(), &salt)))?;
+    match channel.recv()? {
+        SrpMessage::Verdict(verdict) => Ok(verdict),
+        other => bail!("Expected a verdict, got {:?}", other),
+    }
+}
+
+pub struct CapturedSrpProof {
+    pub identity: String,
+    pub client_public: BigUint,
+    pub salt: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+/// Challenge 38: poses as a simplified SRP server with b = 1 and u = 1, so
+/// the client's S is A·g^x and its proof can be checked offline against
+/// password guesses.
+pub fn simple_srp_mitm(params: &SrpParams, channel: &Channel) -> Result<CapturedSrpProof> {
+    let (identity, client_public) = match channel.recv()? {
+        SrpMessage::Hello { identity, public } => (identity, public),
+        other => bail!("Expected a hello, got {:?}", other),
+    };
+    let salt = gen_rand_key(16);
+    channel.send(SrpMessage::SimpleChallenge {
+        salt: salt.clone(),
+        public: params.g.clone(),
+        u: BigUint::one(),
+    })?;
+
+    let proof = match channel.recv()? {
+        SrpMessage::Proof(proof) => proof,
+        other => bail!("Expected a proof, got {:?}", other),
+    };
+    channel.send(SrpMessage::Verdict(true))?;
+    Ok(CapturedSrpProof {
+        identity,
+        client_public,
+        salt,
+        proof,
+    })
+}
+
+/// Splits `words` across `threads` workers, stopping all of them as soon
+/// as one finds the password behind a captured simplified SRP proof.
+pub fn crack_simple_srp(
+    params: &SrpParams,
+    captured: &CapturedSrpProof,
+    words: &[String],
+    threads: usize,
+) -> Option<String> {
+    let found = AtomicBool::new(false);
+    let matches = |word: &String| {
+        let x = params.simple_private_key(&captured.salt, word);
+        let secret = &captured.client_public * modexp(&params.g, &x, &params.n) % &params.n;
+        session_proof(&secret, &captured.salt) == captured.proof
+    };
+
+    let chunk_size = words.len().div_ceil(threads.max(1)).max(1);
+    std::thread::scope(|scope| {
+        let workers = words
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(|| {
+                    let word = chunk
+                        .iter()
+                        .take_while(|_| !found.load(Ordering::Relaxed))
+                        .find(|word| matches(word));
+                    if word.is_some() {
+                        found.store(true, Ordering::Relaxed);
+                    }
+                    word
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().ok().flatten())
+            .next()
+            .cloned()
+    })
+}
@@ -106,9 +106,33 @@ impl Decoder for Base64 {
     }
 }
 
-pub trait PKCS7 {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    Empty,
+    ZeroByte,
+    Oversize(u8),
+    Inconsistent(u8),
+}
+
+impl std::fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaddingError::Empty => write!(f, "Can't strip padding from an empty buffer"),
+            PaddingError::ZeroByte => write!(f, "Padding byte can't be zero"),
+            PaddingError::Oversize(n) => write!(f, "Padding length {} is too large", n),
+            PaddingError::Inconsistent(n) => {
+                write!(f, "Padding bytes don't all match padding length {}", n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+pub trait PKCS7: Sized {
     fn pad(self, block_size: u8) -> Self;
     fn strip(self) -> Self;
+    fn try_strip(self, block_size: u8) -> Result<Self, PaddingError>;
 }
 
 impl PKCS7 for Vec<u8> {
@@ -119,7 +143,7 @@ impl PKCS7 for Vec<u8> {
         } else {
             excess as u8
         };
-        self.extend(std::iter::repeat(excess).take(excess.into()));
+        self.extend(std::iter::repeat_n(excess, excess.into()));
         self
     }
 
@@ -129,12 +153,31 @@ impl PKCS7 for Vec<u8> {
         } else {
             return self;
         };
-        if self[self.len() - padding..]
-            .iter()
-            .all(|byte| *byte == padding as u8)
+        if padding <= self.len()
+            && self[self.len() - padding..]
+                .iter()
+                .all(|byte| *byte == padding as u8)
         {
-            self.truncate(self.len() - padding as usize);
+            self.truncate(self.len() - padding);
         }
         self
     }
+
+    fn try_strip(mut self, block_size: u8) -> Result<Vec<u8>, PaddingError> {
+        let padding = *self.last().ok_or(PaddingError::Empty)?;
+        if padding == 0 {
+            return Err(PaddingError::ZeroByte);
+        }
+        if padding > block_size || padding as usize > self.len() {
+            return Err(PaddingError::Oversize(padding));
+        }
+        if self[self.len() - padding as usize..]
+            .iter()
+            .any(|byte| *byte != padding)
+        {
+            return Err(PaddingError::Inconsistent(padding));
+        }
+        self.truncate(self.len() - padding as usize);
+        Ok(self)
+    }
 }
@@ -0,0 +1,44 @@
+use std::cell::Cell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub trait Clock {
+    fn now(&self) -> u64;
+    fn sleep(&self, secs: u64);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, secs: u64) {
+        std::thread::sleep(Duration::from_secs(secs));
+    }
+}
+
+pub struct FakeClock {
+    now: Cell<u64>,
+}
+
+impl FakeClock {
+    pub fn new(now: u64) -> Self {
+        FakeClock {
+            now: Cell::new(now),
+        }
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    fn sleep(&self, secs: u64) {
+        self.now.set(self.now.get() + secs);
+    }
+}
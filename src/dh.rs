@@ -0,0 +1,81 @@
+use crate::hash::sha1::Sha1;
+use crate::hash::MerkleDamgard;
+use anyhow::{ensure, Result};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+
+pub mod protocol;
+
+const NIST_PRIME: &str = "\
+ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff";
+
+lazy_static! {
+    pub static ref NIST_P: BigUint = BigUint::parse_bytes(NIST_PRIME.as_bytes(), 16).unwrap();
+    pub static ref NIST_G: BigUint = BigUint::from(2_u32);
+}
+
+/// Right-to-left square-and-multiply.
+pub fn modexp(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    if modulus.is_one() {
+        return BigUint::zero();
+    }
+
+    let mut result = BigUint::one();
+    let mut base = base % modulus;
+    for i in 0..exponent.bits() {
+        if exponent.bit(i) {
+            result = result * &base % modulus;
+        }
+        base = &base * &base % modulus;
+    }
+    result
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhGroup {
+    pub p: BigUint,
+    pub g: BigUint,
+}
+
+impl DhGroup {
+    /// Rejects moduli below 2, which leave no private keys to draw and
+    /// would have `modexp` divide by zero.
+    pub fn new(p: BigUint, g: BigUint) -> Result<Self> {
+        ensure!(p > BigUint::one(), "Modulus {p} is too small for a group");
+        Ok(DhGroup { p, g })
+    }
+
+    /// The 1536-bit MODP group from RFC 3526 with generator 2.
+    pub fn nist() -> Self {
+        DhGroup {
+            p: NIST_P.clone(),
+            g: NIST_G.clone(),
+        }
+    }
+
+    pub fn generate_keypair(&self) -> KeyPair {
+        let private = rand::thread_rng().gen_biguint_range(&BigUint::one(), &self.p);
+        let public = modexp(&self.g, &private, &self.p);
+        KeyPair { private, public }
+    }
+
+    pub fn shared_secret(&self, keypair: &KeyPair, other_public: &BigUint) -> BigUint {
+        modexp(other_public, &keypair.private, &self.p)
+    }
+}
+
+pub struct KeyPair {
+    private: BigUint,
+    pub public: BigUint,
+}
+
+/// Turns a shared secret into an AES-128 key: the first 16 bytes of the
+/// SHA-1 of its big-endian encoding.
+pub fn derive_key(secret: &BigUint) -> Vec<u8> {
+    Sha1::digest(&secret.to_bytes_be())[..16].to_vec()
+}
@@ -0,0 +1,231 @@
+use super::{derive_key, DhGroup};
+use crate::aes::{AesCbc128, Cipher};
+use crate::buffer::*;
+use crate::gen_rand_key;
+use anyhow::{anyhow, bail, Result};
+use num_bigint::BigUint;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Negotiate {
+        p: BigUint,
+        g: BigUint,
+    },
+    Ack {
+        p: BigUint,
+        g: BigUint,
+    },
+    KeyExchange {
+        p: BigUint,
+        g: BigUint,
+        public: BigUint,
+    },
+    PublicKey(BigUint),
+    Encrypted {
+        ciphertext: Vec<u8>,
+        iv: Vec<u8>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    AliceToBob,
+    BobToAlice,
+}
+
+/// Whoever sits on the wire between Alice and Bob. Every message passes
+/// through `relay` and whatever it returns is what gets delivered.
+pub trait Middle {
+    fn relay(&mut self, direction: Direction, message: Message) -> Result<Message>;
+}
+
+/// An honest network that delivers everything untouched.
+pub struct Relay;
+
+impl Middle for Relay {
+    fn relay(&mut self, _direction: Direction, message: Message) -> Result<Message> {
+        Ok(message)
+    }
+}
+
+pub fn encrypt_message(secret: &BigUint, plaintext: &[u8]) -> Result<Message> {
+    let iv = gen_rand_key(AesCbc128::BLOCK_SIZE);
+    let plaintext = plaintext.to_vec().pad(AesCbc128::BLOCK_SIZE as u8);
+    let ciphertext = AesCbc128::encrypt(&derive_key(secret), Some(&iv), &plaintext)?;
+    Ok(Message::Encrypted { ciphertext, iv })
+}
+
+pub fn decrypt_message(secret: &BigUint, message: &Message) -> Result<Vec<u8>> {
+    match message {
+        Message::Encrypted { ciphertext, iv } => {
+            let plaintext = AesCbc128::decrypt(&derive_key(secret), Some(iv), ciphertext)?;
+            Ok(plaintext.try_strip(AesCbc128::BLOCK_SIZE as u8)?)
+        }
+        other => bail!("Expected an encrypted message, got {:?}", other),
+    }
+}
+
+/// What each end saw of the exchange: the message as Bob decrypted it and
+/// the echo as Alice decrypted it.
+pub struct EchoTranscript {
+    pub received: Vec<u8>,
+    pub echoed: Vec<u8>,
+}
+
+/// Runs the echo protocol with Alice and Bob on their own threads, talking
+/// over channels that are both routed through `middle`. Alice opens with
+/// p, g and her public key in a single message.
+pub fn run_echo<M: Middle>(
+    group: &DhGroup,
+    message: &[u8],
+    middle: &mut M,
+) -> Result<EchoTranscript> {
+    let (group, message) = (group.clone(), message.to_vec());
+    simulate(middle, move |tx, rx| alice(&group, &message, tx, rx), bob)
+}
+
+/// Same as `run_echo`, but the group is negotiated first. Bob acknowledges
+/// by sending back the group he'll use and Alice carries on with that one,
+/// before either of them sends a public key.
+pub fn run_negotiated_echo<M: Middle>(
+    group: &DhGroup,
+    message: &[u8],
+    middle: &mut M,
+) -> Result<EchoTranscript> {
+    let (group, message) = (group.clone(), message.to_vec());
+    simulate(
+        middle,
+        move |tx, rx| negotiating_alice(&group, &message, tx, rx),
+        negotiating_bob,
+    )
+}
+
+fn simulate<M, A, B>(middle: &mut M, alice: A, bob: B) -> Result<EchoTranscript>
+where
+    M: Middle,
+    A: FnOnce(Sender<Message>, Receiver<Message>) -> Result<Vec<u8>> + Send + 'static,
+    B: FnOnce(Sender<Message>, Receiver<Message>) -> Result<Vec<u8>> + Send + 'static,
+{
+    let (alice_tx, from_alice) = channel();
+    let (to_alice, alice_rx) = channel();
+    let (bob_tx, from_bob) = channel();
+    let (to_bob, bob_rx) = channel();
+
+    let alice = thread::spawn(move || alice(alice_tx, alice_rx));
+    let bob = thread::spawn(move || bob(bob_tx, bob_rx));
+
+    let relayed = (|| -> Result<()> {
+        while let Ok(message) = from_alice.recv() {
+            to_bob.send(middle.relay(Direction::AliceToBob, message)?)?;
+            match from_bob.recv() {
+                Ok(message) => to_alice.send(middle.relay(Direction::BobToAlice, message)?)?,
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    })();
+    drop((to_alice, to_bob));
+
+    let received = bob.join().map_err(|_| anyhow!("Bob panicked"));
+    let echoed = alice.join().map_err(|_| anyhow!("Alice panicked"));
+    relayed?;
+    Ok(EchoTranscript {
+        received: received??,
+        echoed: echoed??,
+    })
+}
+
+fn alice(
+    group: &DhGroup,
+    message: &[u8],
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+) -> Result<Vec<u8>> {
+    let keypair = group.generate_keypair();
+    tx.send(Message::KeyExchange {
+        p: group.p.clone(),
+        g: group.g.clone(),
+        public: keypair.public.clone(),
+    })?;
+
+    let secret = match rx.recv()? {
+        Message::PublicKey(public) => group.shared_secret(&keypair, &public),
+        other => bail!("Alice expected a public key, got {:?}", other),
+    };
+    send_and_await_echo(&secret, message, &tx, &rx)
+}
+
+fn bob(tx: Sender<Message>, rx: Receiver<Message>) -> Result<Vec<u8>> {
+    let (group, public) = match rx.recv()? {
+        Message::KeyExchange { p, g, public } => (DhGroup::new(p, g)?, public),
+        other => bail!("Bob expected a key exchange, got {:?}", other),
+    };
+    let keypair = group.generate_keypair();
+    tx.send(Message::PublicKey(keypair.public.clone()))?;
+
+    let secret = group.shared_secret(&keypair, &public);
+    echo(&secret, &tx, &rx)
+}
+
+fn negotiating_alice(
+    group: &DhGroup,
+    message: &[u8],
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+) -> Result<Vec<u8>> {
+    tx.send(Message::Negotiate {
+        p: group.p.clone(),
+        g: group.g.clone(),
+    })?;
+    let group = match rx.recv()? {
+        Message::Ack { p, g } => DhGroup::new(p, g)?,
+        other => bail!("Alice expected an ack, got {:?}", other),
+    };
+
+    let keypair = group.generate_keypair();
+    tx.send(Message::PublicKey(keypair.public.clone()))?;
+    let secret = match rx.recv()? {
+        Message::PublicKey(public) => group.shared_secret(&keypair, &public),
+        other => bail!("Alice expected a public key, got {:?}", other),
+    };
+    send_and_await_echo(&secret, message, &tx, &rx)
+}
+
+fn negotiating_bob(tx: Sender<Message>, rx: Receiver<Message>) -> Result<Vec<u8>> {
+    let group = match rx.recv()? {
+        Message::Negotiate { p, g } => DhGroup::new(p, g)?,
+        other => bail!("Bob expected a group, got {:?}", other),
+    };
+    tx.send(Message::Ack {
+        p: group.p.clone(),
+        g: group.g.clone(),
+    })?;
+
+    let public = match rx.recv()? {
+        Message::PublicKey(public) => public,
+        other => bail!("Bob expected a public key, got {:?}", other),
+    };
+    let keypair = group.generate_keypair();
+    tx.send(Message::PublicKey(keypair.public.clone()))?;
+
+    let secret = group.shared_secret(&keypair, &public);
+    echo(&secret, &tx, &rx)
+}
+
+fn send_and_await_echo(
+    secret: &BigUint,
+    message: &[u8],
+    tx: &Sender<Message>,
+    rx: &Receiver<Message>,
+) -> Result<Vec<u8>> {
+    tx.send(encrypt_message(secret, message)?)?;
+    decrypt_message(secret, &rx.recv()?)
+}
+
+fn echo(secret: &BigUint, tx: &Sender<Message>, rx: &Receiver<Message>) -> Result<Vec<u8>> {
+    let received = decrypt_message(secret, &rx.recv()?)?;
+    tx.send(encrypt_message(secret, &received)?)?;
+    Ok(received)
+}
@@ -0,0 +1,122 @@
+use anyhow::{ensure, Result};
+
+pub mod md4;
+pub mod sha1;
+pub mod sha256;
+
+/// What a hash has absorbed so far: its chaining words, the input that
+/// doesn't fill a block yet and the total message length in bytes.
+#[derive(Clone)]
+pub struct HashState<S> {
+    state: S,
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+pub trait MerkleDamgard: Sized {
+    const BLOCK_SIZE: usize;
+    const DIGEST_SIZE: usize;
+    const BIG_ENDIAN: bool;
+
+    /// The chaining words, e.g. `[u32; 5]` for SHA-1.
+    type State: AsRef<[u32]> + AsMut<[u32]> + Copy;
+    const INITIAL_STATE: Self::State;
+
+    fn from_hash_state(hash_state: HashState<Self::State>) -> Self;
+    fn hash_state(&mut self) -> &mut HashState<Self::State>;
+
+    /// Mixes one full block into the chaining words.
+    fn compress(state: &mut Self::State, block: &[u8]);
+
+    fn new() -> Self {
+        Self::from_state(Self::INITIAL_STATE, 0)
+    }
+
+    /// Resumes hashing from an arbitrary internal state, as if `length`
+    /// bytes had already been processed. `length` should be a multiple of
+    /// the block size for the result to match a real computation.
+    fn from_state(state: Self::State, length: u64) -> Self {
+        Self::from_hash_state(HashState {
+            state,
+            buffer: Vec::with_capacity(Self::BLOCK_SIZE),
+            length,
+        })
+    }
+
+    fn from_digest(digest: &[u8], length: u64) -> Result<Self> {
+        ensure!(
+            digest.len() == Self::DIGEST_SIZE,
+            "Unexpected digest length {}, expected {}",
+            digest.len(),
+            Self::DIGEST_SIZE
+        );
+        let mut state = Self::INITIAL_STATE;
+        for (word, chunk) in state.as_mut().iter_mut().zip(digest.chunks_exact(4)) {
+            let bytes = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            *word = if Self::BIG_ENDIAN {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            };
+        }
+        Ok(Self::from_state(state, length))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let hash_state = self.hash_state();
+        hash_state.length += data.len() as u64;
+        hash_state.buffer.extend_from_slice(data);
+        let full = hash_state.buffer.len() - hash_state.buffer.len() % Self::BLOCK_SIZE;
+        for block in hash_state.buffer[..full].chunks_exact(Self::BLOCK_SIZE) {
+            Self::compress(&mut hash_state.state, block);
+        }
+        hash_state.buffer.drain(..full);
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Self::padding(self.hash_state().length);
+        self.update(&padding);
+        self.hash_state()
+            .state
+            .as_ref()
+            .iter()
+            .flat_map(|word| {
+                if Self::BIG_ENDIAN {
+                    word.to_be_bytes()
+                } else {
+                    word.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// The padding appended to a message of `length` bytes before its final
+    /// compression, which is also the glue a length extension has to forge.
+    fn padding(length: u64) -> Vec<u8> {
+        let zeroes =
+            (Self::BLOCK_SIZE - (length as usize + 9) % Self::BLOCK_SIZE) % Self::BLOCK_SIZE;
+        let bits = length.wrapping_mul(8);
+        let bits = if Self::BIG_ENDIAN {
+            bits.to_be_bytes()
+        } else {
+            bits.to_le_bytes()
+        };
+        std::iter::once(0x80)
+            .chain(std::iter::repeat_n(0, zeroes))
+            .chain(bits)
+            .collect()
+    }
+}
+
+pub fn secret_prefix_mac<H: MerkleDamgard>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
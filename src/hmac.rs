@@ -0,0 +1,19 @@
+use crate::hash::MerkleDamgard;
+
+pub fn hmac<H: MerkleDamgard>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut key = if key.len() > H::BLOCK_SIZE {
+        H::digest(key)
+    } else {
+        key.to_vec()
+    };
+    key.resize(H::BLOCK_SIZE, 0);
+
+    let mut inner = H::new();
+    inner.update(&key.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+
+    let mut outer = H::new();
+    outer.update(&key.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
@@ -0,0 +1,31 @@
+use anyhow::{bail, Result};
+
+pub fn parse(input: &str) -> Result<Vec<(String, String)>> {
+    input
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Ok((key.to_owned(), value.to_owned())),
+            None => bail!("Malformed key-value pair \"{}\"", pair),
+        })
+        .collect()
+}
+
+pub fn encode<K, V>(pairs: &[(K, V)]) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", sanitize(key), sanitize(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn sanitize<S: AsRef<str>>(input: S) -> String {
+    input
+        .as_ref()
+        .chars()
+        .filter(|c| !matches!(c, '&' | '='))
+        .collect()
+}
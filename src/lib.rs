@@ -1,5 +1,16 @@
 pub mod aes;
+pub mod attacks;
 pub mod buffer;
+pub mod clock;
+pub mod dh;
+pub mod hash;
+pub mod hmac;
+pub mod kv;
+pub mod mt19937;
+pub mod rsa;
+pub mod server;
+pub mod services;
+pub mod srp;
 pub mod xorcrypt;
 
 use crate::buffer::*;
@@ -36,13 +47,31 @@ where
     let input = File::open(path)?;
     let res = BufReader::new(input)
         .lines()
-        .filter_map(std::result::Result::ok)
+        .map_while(std::result::Result::ok)
         .flat_map(|line| line.decode::<Base64>())
         .flatten()
         .collect();
     Ok(res)
 }
 
+pub fn decode_b64_lines<P>(path: P) -> Result<Vec<Vec<u8>>>
+where
+    P: AsRef<Path>,
+{
+    let input = File::open(path)?;
+    BufReader::new(input)
+        .lines()
+        .map_while(std::result::Result::ok)
+        .map(|line| line.decode::<Base64>())
+        .collect()
+}
+
 pub fn gen_rand_key(key_len: usize) -> Vec<u8> {
-    std::iter::repeat_with(rand::random).take(key_len).collect()
+    gen_rand_key_with(&mut rand::thread_rng(), key_len)
+}
+
+pub fn gen_rand_key_with<R: rand::RngCore>(rng: &mut R, key_len: usize) -> Vec<u8> {
+    let mut key = vec![0; key_len];
+    rng.fill_bytes(&mut key);
+    key
 }
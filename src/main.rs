@@ -3,6 +3,9 @@ use std::path::PathBuf;
 
 mod set1;
 mod set2;
+mod set3;
+mod set4;
+mod set5;
 
 pub struct CryptopalArgs {
     pub challenge: usize,
@@ -13,11 +16,14 @@ impl CryptopalArgs {
     fn from_args() -> Self {
         let mut args = std::env::args();
         let command = args.next().expect("Couldn't get command");
-        let challenge = args.next().map(|n| n.parse().ok()).flatten();
+        let challenge = args.next().and_then(|n| n.parse().ok());
         let inputfile = args.next().map(PathBuf::from);
 
         if let Some(challenge) = challenge {
-            CryptopalArgs { challenge, inputfile }
+            CryptopalArgs {
+                challenge,
+                inputfile,
+            }
         } else {
             eprintln!("Usage: {command} <challenge_number> [inputfile]");
             std::process::exit(1);
@@ -31,6 +37,9 @@ fn main() -> Result<()> {
     match args.challenge {
         n if (1..=8).contains(&n) => set1::run(&args)?,
         n if (9..=16).contains(&n) => set2::run(&args)?,
+        n if (17..=24).contains(&n) => set3::run(&args)?,
+        n if (25..=32).contains(&n) => set4::run(&args)?,
+        n if (33..=40).contains(&n) => set5::run(&args)?,
         n => bail!("Challenge {n} doesn't exist"),
     };
 
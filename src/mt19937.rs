@@ -0,0 +1,277 @@
+use crate::aes::Cipher;
+use crate::gen_rand_key_with;
+use crate::xorcrypt::XORCrypto;
+use anyhow::{ensure, Result};
+use rand::{Error, RngCore, SeedableRng};
+
+pub const DEFAULT_SEED: u32 = 5489;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+pub struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Default for Mt19937 {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Self {
+        let mut mt = Mt19937 {
+            state: [0; N],
+            index: N,
+        };
+        mt.seed(seed);
+        mt
+    }
+
+    pub fn seed(&mut self, seed: u32) {
+        self.state[0] = seed;
+        for i in 1..N {
+            let prev = self.state[i - 1];
+            self.state[i] = 1_812_433_253_u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        self.index = N;
+    }
+
+    pub fn clone_from_outputs(outputs: &[u32; N]) -> Self {
+        let mut state = [0; N];
+        for (word, &output) in state.iter_mut().zip(outputs.iter()) {
+            *word = untemper(output);
+        }
+        Mt19937 { state, index: N }
+    }
+
+    /// Clones a generator from its output serialized the way `fill_bytes`
+    /// does it, starting on an output boundary. The clone continues where
+    /// the observed bytes end.
+    pub fn clone_from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() >= 4 * N,
+            "Need at least {} bytes of output, got {}",
+            4 * N,
+            bytes.len()
+        );
+
+        let words = bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect::<Vec<_>>();
+        let mut outputs = [0; N];
+        outputs.copy_from_slice(&words[words.len() - N..]);
+
+        let mut mt = Self::clone_from_outputs(&outputs);
+        if !bytes.len().is_multiple_of(4) {
+            mt.next_u32();
+        }
+        Ok(mt)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper(y)
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 0 { 0 } else { MATRIX_A };
+            self.state[i] = self.state[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^= y >> 18;
+    y
+}
+
+pub fn untemper(mut y: u32) -> u32 {
+    y = undo_right_shift_xor(y, 18);
+    y = undo_left_shift_xor(y, 15, 0xefc6_0000);
+    y = undo_left_shift_xor(y, 7, 0x9d2c_5680);
+    y = undo_right_shift_xor(y, 11);
+    y
+}
+
+fn undo_right_shift_xor(y: u32, shift: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ (x >> shift);
+    }
+    x
+}
+
+fn undo_left_shift_xor(y: u32, shift: u32, mask: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ ((x << shift) & mask);
+    }
+    x
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = Mt19937::next_u32(self) as u64;
+        let high = Mt19937::next_u32(self) as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = Mt19937::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937 {
+    type Seed = [u8; 4];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u32::from_le_bytes(seed))
+    }
+}
+
+const N64: usize = 312;
+const M64: usize = 156;
+const MATRIX_A64: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK64: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK64: u64 = 0x0000_0000_7fff_ffff;
+
+pub struct Mt19937_64 {
+    state: [u64; N64],
+    index: usize,
+}
+
+impl Default for Mt19937_64 {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED as u64)
+    }
+}
+
+impl Mt19937_64 {
+    pub fn new(seed: u64) -> Self {
+        let mut mt = Mt19937_64 {
+            state: [0; N64],
+            index: N64,
+        };
+        mt.seed(seed);
+        mt
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.state[0] = seed;
+        for i in 1..N64 {
+            let prev = self.state[i - 1];
+            self.state[i] = 6_364_136_223_846_793_005_u64
+                .wrapping_mul(prev ^ (prev >> 62))
+                .wrapping_add(i as u64);
+        }
+        self.index = N64;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= N64 {
+            self.twist();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+
+        y ^= (y >> 29) & 0x5555_5555_5555_5555;
+        y ^= (y << 17) & 0x71d6_7fff_eda6_0000;
+        y ^= (y << 37) & 0xfff7_eee0_0000_0000;
+        y ^= y >> 43;
+        y
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N64 {
+            let y = (self.state[i] & UPPER_MASK64) | (self.state[(i + 1) % N64] & LOWER_MASK64);
+            let mag = if y & 1 == 0 { 0 } else { MATRIX_A64 };
+            self.state[i] = self.state[(i + M64) % N64] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    fn next_u32(&mut self) -> u32 {
+        (Mt19937_64::next_u64(self) >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Mt19937_64::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = Mt19937_64::next_u64(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937_64 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u64::from_le_bytes(seed))
+    }
+}
+
+pub struct Mt19937Cipher;
+
+impl Mt19937Cipher {
+    pub fn keystream(key: &[u8], len: usize) -> Result<Vec<u8>> {
+        ensure!(
+            key.len() == 2,
+            "Unexpected key length {} for MT19937 cipher, expected a 16 bit seed",
+            key.len()
+        );
+        let seed = u16::from_le_bytes([key[0], key[1]]);
+        Ok(gen_rand_key_with(&mut Mt19937::new(seed as u32), len))
+    }
+}
+
+impl Cipher for Mt19937Cipher {
+    fn encrypt(key: &[u8], _iv: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>> {
+        data.xor(&Self::keystream(key, data.len())?)
+    }
+
+    fn decrypt(key: &[u8], iv: Option<&[u8]>, data: &[u8]) -> Result<Vec<u8>> {
+        Self::encrypt(key, iv, data)
+    }
+}
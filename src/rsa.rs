@@ -0,0 +1,155 @@
+use crate::dh::modexp;
+use anyhow::{ensure, Result};
+use num_bigint::{BigInt, BigUint, RandBigInt, Sign};
+use num_traits::{One, Zero};
+
+const SMALL_PRIMES: [u32; 24] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+];
+const MILLER_RABIN_ROUNDS: usize = 40;
+/// Small enough for quick tests, and large enough that the modulus is
+/// bigger than any `u32` public exponent.
+const MIN_MODULUS_BITS: u64 = 64;
+
+/// The inverse of `a` mod `m` by the extended Euclidean algorithm, if `a`
+/// and `m` are coprime.
+pub fn invmod(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+    let m_signed = BigInt::from_biguint(Sign::Plus, m.clone());
+    let (mut r0, mut r1) = (m_signed.clone(), BigInt::from_biguint(Sign::Plus, a % m));
+    let (mut t0, mut t1) = (BigInt::zero(), BigInt::one());
+    while !r1.is_zero() {
+        let q = &r0 / &r1;
+        let r2 = &r0 - &q * &r1;
+        let t2 = &t0 - &q * &t1;
+        r0 = std::mem::replace(&mut r1, r2);
+        t0 = std::mem::replace(&mut t1, t2);
+    }
+
+    if !r0.is_one() {
+        return None;
+    }
+    let t0 = ((t0 % &m_signed) + &m_signed) % &m_signed;
+    t0.to_biguint()
+}
+
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let two = BigUint::from(2_u32);
+    if *n < two {
+        return false;
+    }
+    for &p in SMALL_PRIMES.iter() {
+        let p = BigUint::from(p);
+        if *n == p {
+            return true;
+        }
+        if (n % &p).is_zero() {
+            return false;
+        }
+    }
+
+    let n_minus_one = n - 1_u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = modexp(&a, &d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = modexp(&x, &two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// A random prime of exactly `bits` bits, with the top two bits set so the
+/// product of two of them has exactly twice as many.
+pub fn gen_prime(bits: u64) -> Result<BigUint> {
+    ensure!(bits >= 2, "Can't generate a {bits} bit prime");
+    let mut rng = rand::thread_rng();
+    loop {
+        let mut candidate = rng.gen_biguint(bits);
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate, MILLER_RABIN_ROUNDS) {
+            return Ok(candidate);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    pub e: BigUint,
+    pub n: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaPrivateKey {
+    pub d: BigUint,
+    pub n: BigUint,
+}
+
+pub struct RsaKeyPair {
+    pub public: RsaPublicKey,
+    pub private: RsaPrivateKey,
+}
+
+impl RsaKeyPair {
+    /// Generates a key with a `bits` bit modulus and public exponent `e`,
+    /// usually 3 or 65537. Primes for which e isn't invertible mod
+    /// totient are thrown away and drawn again.
+    pub fn generate(bits: u64, e: u32) -> Result<Self> {
+        ensure!(
+            bits >= MIN_MODULUS_BITS && bits.is_multiple_of(2),
+            "Unsupported modulus size {bits}, need an even size of at least {MIN_MODULUS_BITS}"
+        );
+        ensure!(
+            e >= 3 && !e.is_multiple_of(2),
+            "Public exponent {e} must be odd and at least 3"
+        );
+
+        let e = BigUint::from(e);
+        loop {
+            let (p, q) = (gen_prime(bits / 2)?, gen_prime(bits / 2)?);
+            if p == q {
+                continue;
+            }
+            let totient = (&p - 1_u32) * (&q - 1_u32);
+            if let Some(d) = invmod(&e, &totient) {
+                let n = p * q;
+                return Ok(RsaKeyPair {
+                    public: RsaPublicKey { e, n: n.clone() },
+                    private: RsaPrivateKey { d, n },
+                });
+            }
+        }
+    }
+}
+
+impl RsaPublicKey {
+    pub fn encrypt(&self, message: &BigUint) -> Result<BigUint> {
+        ensure!(message < &self.n, "Message doesn't fit in the modulus");
+        Ok(modexp(message, &self.e, &self.n))
+    }
+}
+
+impl RsaPrivateKey {
+    pub fn decrypt(&self, ciphertext: &BigUint) -> Result<BigUint> {
+        ensure!(
+            ciphertext < &self.n,
+            "Ciphertext doesn't fit in the modulus"
+        );
+        Ok(modexp(ciphertext, &self.d, &self.n))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::buffer::*;
+use crate::hash::sha1::Sha1;
+use crate::hmac::hmac;
+use crate::kv;
+use anyhow::{bail, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A local stand-in for the challenge 31 web app. It answers
+/// `GET /test?file=..&signature=..` with 200 when the signature is the
+/// HMAC-SHA1 of the file name, comparing one byte at a time and sleeping
+/// `delay` after every matching byte.
+pub struct HmacServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl HmacServer {
+    pub fn start(key: &[u8], delay: Duration) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let key = key.to_vec();
+        let stopped = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = handle_request(stream, &key, delay);
+                }
+            }
+        });
+
+        Ok(HmacServer {
+            addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for HmacServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_request(stream: TcpStream, key: &[u8], delay: Duration) -> Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let valid = match request_line.split_whitespace().nth(1) {
+        Some(target) => match target.strip_prefix("/test?") {
+            Some(query) => check_query(query, key, delay).unwrap_or(false),
+            None => false,
+        },
+        None => false,
+    };
+
+    let status = if valid {
+        "200 OK"
+    } else {
+        "500 Internal Server Error"
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    )?;
+    Ok(())
+}
+
+fn check_query(query: &str, key: &[u8], delay: Duration) -> Result<bool> {
+    let params = kv::parse(query)?;
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    match (param("file"), param("signature")) {
+        (Some(file), Some(signature)) => {
+            let expected = hmac::<Sha1>(key, file.as_bytes());
+            Ok(insecure_compare(
+                &expected,
+                &signature.decode::<Hex>()?,
+                delay,
+            ))
+        }
+        _ => bail!("Missing file or signature"),
+    }
+}
+
+fn insecure_compare(expected: &[u8], actual: &[u8], delay: Duration) -> bool {
+    for (e, a) in expected.iter().zip(actual) {
+        if e != a {
+            return false;
+        }
+        std::thread::sleep(delay);
+    }
+    expected.len() == actual.len()
+}
+
+pub fn check_signature(addr: SocketAddr, file: &str, signature: &[u8]) -> Result<bool> {
+    let mut stream = TcpStream::connect(addr)?;
+    let request = format!(
+        "GET /test?file={}&signature={} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        file,
+        signature.encode::<Hex>(),
+        addr
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(true),
+        Some("500") => Ok(false),
+        _ => bail!("Unexpected response \"{}\"", status_line.trim()),
+    }
+}
@@ -0,0 +1,313 @@
+use crate::aes::{AesCbc128, AesCtr128, AesEcb128, Cipher};
+use crate::attacks::PaddingOracle;
+use crate::buffer::{PaddingError, PKCS7};
+use crate::clock::Clock;
+use crate::hash::{secret_prefix_mac, MerkleDamgard};
+use crate::mt19937::Mt19937;
+use crate::{gen_rand_key, gen_rand_key_with, kv};
+use anyhow::{ensure, Result};
+use rand::{seq::SliceRandom, Rng};
+use std::marker::PhantomData;
+
+pub struct ProfileService {
+    key: Vec<u8>,
+}
+
+impl Default for ProfileService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProfileService {
+    pub fn new() -> Self {
+        ProfileService {
+            key: gen_rand_key(16),
+        }
+    }
+
+    pub fn profile_for(&self, email: &str) -> Result<Vec<u8>> {
+        let plaintext = encode_profile(email)
+            .into_bytes()
+            .pad(AesEcb128::BLOCK_SIZE as u8);
+        AesEcb128::encrypt(&self.key, None, &plaintext)
+    }
+
+    pub fn decrypt_profile(&self, ciphertext: &[u8]) -> Result<Vec<(String, String)>> {
+        let plaintext = AesEcb128::decrypt(&self.key, None, ciphertext)?
+            .try_strip(AesEcb128::BLOCK_SIZE as u8)?;
+        kv::parse(std::str::from_utf8(&plaintext)?)
+    }
+}
+
+fn encode_profile(email: &str) -> String {
+    kv::encode(&[("email", email), ("uid", "10"), ("role", "user")])
+}
+
+/// Where the email and the role value start in an encoded profile, taken
+/// from the same encoding `profile_for` uses. Returns the length before the
+/// email and the length between the email and the role value.
+pub fn profile_layout() -> (usize, usize) {
+    let profile = encode_profile("");
+    let email_start = profile.find('=').map_or(0, |i| i + 1);
+    let role_start = profile.rfind('=').map_or(0, |i| i + 1);
+    (email_start, role_start - email_start)
+}
+
+pub const COMMENT_PREFIX: &str = "comment1=cooking%20MCs;userdata=";
+const COMMENT_SUFFIX: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
+const ADMIN_TOKEN: &str = ";admin=true;";
+
+pub trait CommentService {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+
+    fn encrypt_userdata(&self, userdata: &str) -> Result<Vec<u8>> {
+        let comment = format!("{}{}{}", COMMENT_PREFIX, quote(userdata), COMMENT_SUFFIX);
+        self.encrypt(comment.as_bytes())
+    }
+
+    fn is_admin(&self, ciphertext: &[u8]) -> Result<bool> {
+        let plaintext = self.decrypt(ciphertext)?;
+        Ok(String::from_utf8_lossy(&plaintext).contains(ADMIN_TOKEN))
+    }
+}
+
+pub struct CbcCommentService {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl Default for CbcCommentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CbcCommentService {
+    pub fn new() -> Self {
+        CbcCommentService {
+            key: gen_rand_key(16),
+            iv: gen_rand_key(AesCbc128::BLOCK_SIZE),
+        }
+    }
+}
+
+impl CommentService for CbcCommentService {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let plaintext = plaintext.to_vec().pad(AesCbc128::BLOCK_SIZE as u8);
+        AesCbc128::encrypt(&self.key, Some(&self.iv), &plaintext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let plaintext = AesCbc128::decrypt(&self.key, Some(&self.iv), ciphertext)?;
+        Ok(plaintext.try_strip(AesCbc128::BLOCK_SIZE as u8)?)
+    }
+}
+
+pub struct CtrCommentService {
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+impl Default for CtrCommentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CtrCommentService {
+    pub fn new() -> Self {
+        CtrCommentService {
+            key: gen_rand_key(16),
+            nonce: gen_rand_key(8),
+        }
+    }
+}
+
+impl CommentService for CtrCommentService {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        AesCtr128::encrypt(&self.key, Some(&self.nonce), plaintext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        AesCtr128::decrypt(&self.key, Some(&self.nonce), ciphertext)
+    }
+}
+
+fn quote(input: &str) -> String {
+    input.replace(';', "%3B").replace('=', "%3D")
+}
+
+#[derive(Debug)]
+pub enum KeyAsIvError {
+    HighAscii(Vec<u8>),
+    Padding(PaddingError),
+    Cipher(anyhow::Error),
+}
+
+impl std::fmt::Display for KeyAsIvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyAsIvError::HighAscii(plaintext) => {
+                write!(f, "Invalid ASCII in plaintext {:?}", plaintext)
+            }
+            KeyAsIvError::Padding(e) => write!(f, "{}", e),
+            KeyAsIvError::Cipher(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeyAsIvError {}
+
+pub struct KeyAsIvService {
+    key: Vec<u8>,
+}
+
+impl Default for KeyAsIvService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyAsIvService {
+    pub fn new() -> Self {
+        KeyAsIvService {
+            key: gen_rand_key(16),
+        }
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn decrypt_ascii(&self, ciphertext: &[u8]) -> Result<Vec<u8>, KeyAsIvError> {
+        let plaintext = AesCbc128::decrypt(&self.key, Some(&self.key), ciphertext)
+            .map_err(KeyAsIvError::Cipher)?;
+        if !plaintext.is_ascii() {
+            return Err(KeyAsIvError::HighAscii(plaintext));
+        }
+        plaintext
+            .try_strip(AesCbc128::BLOCK_SIZE as u8)
+            .map_err(KeyAsIvError::Padding)
+    }
+}
+
+impl CommentService for KeyAsIvService {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let plaintext = plaintext.to_vec().pad(AesCbc128::BLOCK_SIZE as u8);
+        AesCbc128::encrypt(&self.key, Some(&self.key), &plaintext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.decrypt_ascii(ciphertext)?)
+    }
+}
+
+pub struct PaddingOracleServer {
+    key: Vec<u8>,
+    plaintexts: Vec<Vec<u8>>,
+}
+
+impl PaddingOracleServer {
+    pub fn new(plaintexts: Vec<Vec<u8>>) -> Self {
+        PaddingOracleServer {
+            key: gen_rand_key(16),
+            plaintexts,
+        }
+    }
+
+    pub fn encrypt(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let plaintext = self.plaintexts.choose(&mut rand::thread_rng());
+        ensure!(plaintext.is_some(), "No plaintexts to choose from");
+        let plaintext = plaintext.unwrap().clone().pad(AesCbc128::BLOCK_SIZE as u8);
+        let iv = gen_rand_key(AesCbc128::BLOCK_SIZE);
+        let ciphertext = AesCbc128::encrypt(&self.key, Some(&iv), &plaintext)?;
+        Ok((iv, ciphertext))
+    }
+
+    pub fn is_padding_valid(&self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        AesCbc128::decrypt(&self.key, Some(iv), ciphertext)
+            .map(|plaintext| plaintext.try_strip(AesCbc128::BLOCK_SIZE as u8).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+impl PaddingOracle for PaddingOracleServer {
+    fn check_padding(&self, iv: &[u8], ciphertext: &[u8]) -> Result<bool> {
+        Ok(self.is_padding_valid(iv, ciphertext))
+    }
+}
+
+pub fn timestamp_seeded_output<C: Clock>(clock: &C) -> u32 {
+    let mut rng = rand::thread_rng();
+    clock.sleep(rng.gen_range(40, 1001));
+    let mut mt = Mt19937::new(clock.now() as u32);
+    clock.sleep(rng.gen_range(40, 1001));
+    mt.next_u32()
+}
+
+pub fn password_reset_token<C: Clock>(clock: &C) -> Vec<u8> {
+    gen_rand_key_with(&mut Mt19937::new(clock.now() as u32), 16)
+}
+
+pub struct EditableStorage {
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl EditableStorage {
+    pub fn new(plaintext: &[u8]) -> Result<Self> {
+        let key = gen_rand_key(16);
+        let nonce = gen_rand_key(8);
+        let ciphertext = AesCtr128::encrypt(&key, Some(&nonce), plaintext)?;
+        Ok(EditableStorage {
+            key,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    pub fn edit(&mut self, offset: usize, newtext: &[u8]) -> Result<()> {
+        AesCtr128::edit(
+            &mut self.ciphertext,
+            &self.key,
+            &self.nonce,
+            offset,
+            newtext,
+        )
+    }
+}
+
+pub struct MacService<H> {
+    key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: MerkleDamgard> Default for MacService<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: MerkleDamgard> MacService<H> {
+    pub fn new() -> Self {
+        MacService {
+            key: gen_rand_key(rand::thread_rng().gen_range(1, 33)),
+            hash: PhantomData,
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        secret_prefix_mac::<H>(&self.key, message)
+    }
+
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        self.sign(message) == tag
+    }
+}
@@ -54,7 +54,7 @@ fn detect_single_char_xor() -> Result<()> {
     let mut output = None;
     for line in BufReader::new(input)
         .lines()
-        .filter_map(std::result::Result::ok)
+        .map_while(std::result::Result::ok)
     {
         let line = line.decode::<Hex>()?;
         let (guess, freq) = line.guess_xor_key()?;
@@ -109,7 +109,7 @@ fn detect_aes_ecb() -> Result<()> {
     let input = File::open(input)?;
     for (i, line) in BufReader::new(input)
         .lines()
-        .filter_map(|line| line.ok())
+        .map_while(|line| line.ok())
         .enumerate()
     {
         let line = line.decode::<Hex>()?;
@@ -3,8 +3,11 @@ use std::collections::HashSet;
 use crate::CryptopalArgs;
 use anyhow::{bail, Result};
 use cryptopals::aes::{AesCbc128, AesMode, Cipher, Oracle};
+use cryptopals::attacks::{ecb_byte_at_a_time, forge_admin_comment_cbc, forge_admin_profile};
 use cryptopals::buffer::*;
 use cryptopals::decode_b64_file;
+use cryptopals::services::{CbcCommentService, CommentService, ProfileService};
+use rand::Rng;
 
 fn pkcs7_padding() -> Result<()> {
     let input = "YELLOW SUBMARINE".to_owned().into_bytes();
@@ -70,11 +73,83 @@ fn ecb_cbc_oracle() -> Result<()> {
     Ok(())
 }
 
+fn byte_at_a_time_ecb_simple() -> Result<()> {
+    let secret = decode_b64_file("inputs/c12.txt")?;
+    let oracle = Oracle::builder()
+        .with_mode(AesMode::ECB)
+        .with_suffix(&secret)
+        .build();
+
+    let recovered = ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+    println!("Recovered secret:\n{}", String::from_utf8(recovered)?);
+    Ok(())
+}
+
+fn ecb_cut_and_paste() -> Result<()> {
+    let service = ProfileService::new();
+    let forged = forge_admin_profile(|email| service.profile_for(email))?;
+    let profile = service.decrypt_profile(&forged)?;
+
+    for (key, value) in &profile {
+        println!("{key:>8} : {value}");
+    }
+    Ok(())
+}
+
+fn pkcs7_validation() -> Result<()> {
+    for input in [
+        b"ICE ICE BABY\x04\x04\x04\x04".to_vec(),
+        b"ICE ICE BABY\x05\x05\x05\x05".to_vec(),
+        b"ICE ICE BABY\x01\x02\x03\x04".to_vec(),
+    ] {
+        print!("{:?} => ", String::from_utf8(input.clone())?);
+        match input.try_strip(16) {
+            Ok(stripped) => println!("{:?}", String::from_utf8(stripped)?),
+            Err(e) => println!("Error: {e}"),
+        }
+    }
+    Ok(())
+}
+
+fn byte_at_a_time_ecb_harder() -> Result<()> {
+    let secret = decode_b64_file("inputs/c12.txt")?;
+    let oracle = Oracle::builder()
+        .with_mode(AesMode::ECB)
+        .with_prefix_len(rand::thread_rng().gen_range(1, 64))
+        .with_suffix(&secret)
+        .build();
+
+    let recovered = ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+    println!("Recovered secret:\n{}", String::from_utf8(recovered)?);
+    Ok(())
+}
+
+fn cbc_bitflipping() -> Result<()> {
+    let service = CbcCommentService::new();
+    let honest = service.encrypt_userdata(";admin=true;")?;
+    println!(
+        "Quoted userdata grants admin: {}",
+        service.is_admin(&honest)?
+    );
+
+    let forged = forge_admin_comment_cbc(|userdata| service.encrypt_userdata(userdata))?;
+    println!(
+        "Bitflipped ciphertext grants admin: {}",
+        service.is_admin(&forged)?
+    );
+    Ok(())
+}
+
 pub fn run(args: &CryptopalArgs) -> Result<()> {
     match args.challenge {
         9 => pkcs7_padding()?,
         10 => decrypt_cbc()?,
         11 => ecb_cbc_oracle()?,
+        12 => byte_at_a_time_ecb_simple()?,
+        13 => ecb_cut_and_paste()?,
+        14 => byte_at_a_time_ecb_harder()?,
+        15 => pkcs7_validation()?,
+        16 => cbc_bitflipping()?,
         n => bail!("Challenge {n} doesn't exist in set 2"),
     };
 
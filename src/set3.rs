@@ -0,0 +1,164 @@
+use crate::CryptopalArgs;
+use anyhow::{bail, Result};
+use cryptopals::aes::{AesCbc128, AesCtr128, Cipher};
+use cryptopals::attacks::*;
+use cryptopals::buffer::*;
+use cryptopals::clock::{Clock, FakeClock, SystemClock};
+use cryptopals::mt19937::{Mt19937, Mt19937Cipher, DEFAULT_SEED};
+use cryptopals::services::{password_reset_token, timestamp_seeded_output, PaddingOracleServer};
+use cryptopals::xorcrypt::XORCrypto;
+use cryptopals::{decode_b64_lines, gen_rand_key};
+use rand::Rng;
+use std::path::Path;
+
+fn cbc_padding_oracle() -> Result<()> {
+    let server = PaddingOracleServer::new(decode_b64_lines("inputs/c17.txt")?);
+    let (iv, ciphertext) = server.encrypt()?;
+
+    let plaintext = padding_oracle_attack::<AesCbc128, _, _>(
+        &server,
+        &iv,
+        &ciphertext,
+        |block, total, recovered| {
+            println!(
+                "Block {}/{}: {:?}",
+                block + 1,
+                total,
+                String::from_utf8_lossy(recovered)
+            )
+        },
+    )?;
+    println!("Plaintext: {}", String::from_utf8(plaintext)?);
+    Ok(())
+}
+
+fn ctr_stream_mode() -> Result<()> {
+    let ciphertext = "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ=="
+        .decode::<Base64>()?;
+    let nonce = [0; 8];
+    let plaintext = AesCtr128::decrypt(b"YELLOW SUBMARINE", Some(&nonce), &ciphertext)?;
+    println!("Plaintext: {}", String::from_utf8(plaintext)?);
+    Ok(())
+}
+
+fn fixed_nonce_ciphertexts(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let key = gen_rand_key(16);
+    decode_b64_lines(path)?
+        .iter()
+        .map(|line| AesCtr128::encrypt(&key, Some(&[0; 8]), line))
+        .collect()
+}
+
+fn break_fixed_nonce_ctr_truncated() -> Result<()> {
+    let ciphertexts = fixed_nonce_ciphertexts(Path::new("inputs/c19.txt"))?;
+    let keystream = break_fixed_nonce_ctr(&ciphertexts)?;
+    for ciphertext in &ciphertexts {
+        let plaintext = ciphertext[..keystream.len()].xor(&keystream)?;
+        println!("{}", String::from_utf8_lossy(&plaintext));
+    }
+    Ok(())
+}
+
+fn break_fixed_nonce_ctr_statistically(path: &Path) -> Result<()> {
+    let ciphertexts = fixed_nonce_ciphertexts(path)?;
+    let guessed = break_fixed_nonce_ctr_untruncated(&ciphertexts)?;
+    for ciphertext in &ciphertexts {
+        let plaintext = ciphertext
+            .iter()
+            .zip(&guessed)
+            .map(|(byte, (key, confidence))| match confidence {
+                c if *c < 0.25 => '?',
+                _ => char::from(byte ^ key),
+            })
+            .collect::<String>();
+        println!("{plaintext}");
+    }
+    Ok(())
+}
+
+fn mersenne_twister() -> Result<()> {
+    let seed = DEFAULT_SEED;
+    let mut mt = Mt19937::new(seed);
+    println!("First 10 outputs of MT19937 seeded with {seed}:");
+    for _ in 0..10 {
+        println!("{}", mt.next_u32());
+    }
+    Ok(())
+}
+
+fn crack_mt19937_timestamp_seed() -> Result<()> {
+    let clock = FakeClock::new(SystemClock.now());
+    let output = timestamp_seeded_output(&clock);
+
+    let now = clock.now() as u32;
+    match crack_mt19937_seed(output, now - 2000..=now) {
+        Some(seed) => println!("Output {output} came from seed {seed}"),
+        None => bail!("Couldn't find the seed for output {output}"),
+    }
+    Ok(())
+}
+
+fn clone_mt19937() -> Result<()> {
+    let mut mt = Mt19937::new(rand::random());
+    let mut outputs = [0; 624];
+    outputs.iter_mut().for_each(|out| *out = mt.next_u32());
+
+    let mut clone = Mt19937::clone_from_outputs(&outputs);
+    for _ in 0..5 {
+        println!(
+            "original: {:10}, clone: {:10}",
+            mt.next_u32(),
+            clone.next_u32()
+        );
+    }
+    Ok(())
+}
+
+fn mt19937_stream_cipher() -> Result<()> {
+    let seed = rand::random::<u16>();
+    let mut plaintext = gen_rand_key(rand::thread_rng().gen_range(5, 50));
+    plaintext.extend(b"AAAAAAAAAAAAAA");
+    let ciphertext = Mt19937Cipher::encrypt(&seed.to_le_bytes(), None, &plaintext)?;
+
+    match crack_mt19937_cipher_seed(&ciphertext, b"AAAAAAAAAAAAAA") {
+        Some(found) => println!("Recovered seed {found} (actual {seed})"),
+        None => bail!("Couldn't recover the MT19937 cipher seed"),
+    }
+
+    let clock = SystemClock;
+    let token = password_reset_token(&clock);
+    println!(
+        "Reset token {} is time seeded: {}",
+        token.encode::<Hex>(),
+        is_time_seeded_token(&token, &clock, 60)
+    );
+    let token = gen_rand_key(16);
+    println!(
+        "Reset token {} is time seeded: {}",
+        token.encode::<Hex>(),
+        is_time_seeded_token(&token, &clock, 60)
+    );
+    Ok(())
+}
+
+pub fn run(args: &CryptopalArgs) -> Result<()> {
+    match args.challenge {
+        17 => cbc_padding_oracle()?,
+        18 => ctr_stream_mode()?,
+        19 => break_fixed_nonce_ctr_truncated()?,
+        // Expects the published 20.txt as the input file and falls back to
+        // the challenge 19 lines without it.
+        20 => break_fixed_nonce_ctr_statistically(
+            args.inputfile
+                .as_deref()
+                .unwrap_or(Path::new("inputs/c19.txt")),
+        )?,
+        21 => mersenne_twister()?,
+        22 => crack_mt19937_timestamp_seed()?,
+        23 => clone_mt19937()?,
+        24 => mt19937_stream_cipher()?,
+        n => bail!("Challenge {n} doesn't exist in set 3"),
+    };
+
+    Ok(())
+}
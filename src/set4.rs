@@ -0,0 +1,136 @@
+use crate::CryptopalArgs;
+use anyhow::{bail, Result};
+use cryptopals::aes::{AesEcb128, Cipher};
+use cryptopals::attacks::*;
+use cryptopals::buffer::*;
+use cryptopals::hash::md4::Md4;
+use cryptopals::hash::secret_prefix_mac;
+use cryptopals::hash::sha1::{self, Sha1};
+use cryptopals::server::{check_signature, HmacServer};
+use cryptopals::services::{
+    CommentService, CtrCommentService, EditableStorage, KeyAsIvService, MacService,
+};
+use cryptopals::{decode_b64_file, gen_rand_key};
+use std::time::Duration;
+
+fn break_ctr_random_access() -> Result<()> {
+    let plaintext = AesEcb128::decrypt(
+        b"YELLOW SUBMARINE",
+        None,
+        &decode_b64_file("inputs/c7.txt")?,
+    )?;
+    let mut storage = EditableStorage::new(&plaintext)?;
+
+    let ciphertext = storage.ciphertext().to_vec();
+
+    let recovered = ctr_edit_recover(&ciphertext, |offset, newtext| {
+        storage.edit(offset, newtext)?;
+        Ok(storage.ciphertext().to_vec())
+    })?;
+    println!("Recovered plaintext:\n{}", String::from_utf8(recovered)?);
+    Ok(())
+}
+
+fn ctr_bitflipping() -> Result<()> {
+    let service = CtrCommentService::new();
+    let honest = service.encrypt_userdata(";admin=true;")?;
+    println!(
+        "Quoted userdata grants admin: {}",
+        service.is_admin(&honest)?
+    );
+
+    let forged = forge_admin_comment_ctr(|userdata| service.encrypt_userdata(userdata))?;
+    println!(
+        "Bitflipped ciphertext grants admin: {}",
+        service.is_admin(&forged)?
+    );
+    println!(
+        "Decrypted: {}",
+        String::from_utf8_lossy(&service.decrypt(&forged)?)
+    );
+    Ok(())
+}
+
+fn cbc_key_as_iv() -> Result<()> {
+    let service = KeyAsIvService::new();
+    let ciphertext = service.encrypt_userdata("nothing to see here")?;
+
+    let key = recover_key_as_iv(&ciphertext, |ciphertext| service.decrypt_ascii(ciphertext))?;
+    println!("Recovered key: {}", key.encode::<Hex>());
+    println!("Actual key:    {}", service.key().encode::<Hex>());
+    Ok(())
+}
+
+fn sha1_keyed_mac() -> Result<()> {
+    let key = b"YELLOW SUBMARINE";
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = secret_prefix_mac::<Sha1>(key, message);
+    println!("SHA1(key || message) = {}", mac.encode::<Hex>());
+
+    let mut tampered = message.to_vec();
+    tampered[0] ^= 1;
+    println!(
+        "SHA1(key || tampered) = {}",
+        secret_prefix_mac::<Sha1>(key, &tampered).encode::<Hex>()
+    );
+    Ok(())
+}
+
+fn sha1_length_extension() -> Result<()> {
+    let service = MacService::<Sha1>::new();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let tag = service.sign(message);
+
+    let (forged, forged_tag) =
+        length_extension::<Sha1, _>(message, &tag, b";admin=true", 0..64, |message, tag| {
+            service.verify(message, tag)
+        })?;
+    println!("Forged message: {}", String::from_utf8_lossy(&forged));
+    println!("Forged tag:     {}", forged_tag.encode::<Hex>());
+    Ok(())
+}
+
+fn md4_length_extension() -> Result<()> {
+    let service = MacService::<Md4>::new();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let tag = service.sign(message);
+
+    let (forged, forged_tag) =
+        length_extension::<Md4, _>(message, &tag, b";admin=true", 0..64, |message, tag| {
+            service.verify(message, tag)
+        })?;
+    println!("Forged message: {}", String::from_utf8_lossy(&forged));
+    println!("Forged tag:     {}", forged_tag.encode::<Hex>());
+    Ok(())
+}
+
+fn hmac_timing_leak(delay: Duration, samples: usize) -> Result<()> {
+    let server = HmacServer::start(&gen_rand_key(16), delay)?;
+    let file = "foo";
+
+    let signature = timing_attack(sha1::DIGEST_SIZE, samples, |signature| {
+        check_signature(server.addr(), file, signature)
+    })?;
+    println!(
+        "Recovered signature {} for {file}: valid = {}",
+        signature.encode::<Hex>(),
+        check_signature(server.addr(), file, &signature)?
+    );
+    Ok(())
+}
+
+pub fn run(args: &CryptopalArgs) -> Result<()> {
+    match args.challenge {
+        25 => break_ctr_random_access()?,
+        26 => ctr_bitflipping()?,
+        27 => cbc_key_as_iv()?,
+        28 => sha1_keyed_mac()?,
+        29 => sha1_length_extension()?,
+        30 => md4_length_extension()?,
+        31 => hmac_timing_leak(Duration::from_millis(50), 1)?,
+        32 => hmac_timing_leak(Duration::from_millis(5), 5)?,
+        n => bail!("Challenge {n} doesn't exist in set 4"),
+    };
+
+    Ok(())
+}
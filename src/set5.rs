@@ -0,0 +1,183 @@
+use crate::CryptopalArgs;
+use anyhow::{bail, ensure, Result};
+use cryptopals::attacks::{
+    crack_simple_srp, simple_srp_mitm, srp_zero_key_login, DhKeyFixing, DhMaliciousG, MaliciousG,
+};
+use cryptopals::buffer::*;
+use cryptopals::dh::protocol::{run_echo, run_negotiated_echo, Relay};
+use cryptopals::dh::{derive_key, DhGroup};
+use cryptopals::rsa::{invmod, RsaKeyPair};
+use cryptopals::srp::{login, Channel, SimpleSrpClient, SrpClient, SrpParams, SrpServer};
+use num_bigint::BigUint;
+use rand::Rng;
+use std::path::Path;
+
+fn diffie_hellman() -> Result<()> {
+    let toy = DhGroup::new(BigUint::from(37_u32), BigUint::from(5_u32))?;
+    let (a, b) = (toy.generate_keypair(), toy.generate_keypair());
+    println!(
+        "p = 37, g = 5: A = {}, B = {}, s = {} / {}",
+        a.public,
+        b.public,
+        toy.shared_secret(&a, &b.public),
+        toy.shared_secret(&b, &a.public)
+    );
+
+    let group = DhGroup::nist();
+    let (a, b) = (group.generate_keypair(), group.generate_keypair());
+    let s_a = group.shared_secret(&a, &b.public);
+    let s_b = group.shared_secret(&b, &a.public);
+    if s_a != s_b {
+        bail!("Shared secrets don't match");
+    }
+    println!(
+        "NIST group session key: {}",
+        derive_key(&s_a).encode::<Hex>()
+    );
+    Ok(())
+}
+
+fn dh_key_fixing() -> Result<()> {
+    let group = DhGroup::nist();
+    let message = b"Attack at dawn";
+
+    let transcript = run_echo(&group, message, &mut Relay)?;
+    println!(
+        "Honest relay, Bob got {:?}",
+        String::from_utf8_lossy(&transcript.received)
+    );
+
+    let mut attacker = DhKeyFixing::default();
+    let transcript = run_echo(&group, message, &mut attacker)?;
+    println!(
+        "Key-fixing relay, Alice got her echo back: {}",
+        transcript.echoed == message
+    );
+    for (direction, plaintext) in &attacker.intercepted {
+        println!(
+            "Intercepted {:?}: {:?}",
+            direction,
+            String::from_utf8_lossy(plaintext)
+        );
+    }
+    Ok(())
+}
+
+fn dh_malicious_g() -> Result<()> {
+    let group = DhGroup::nist();
+    let message = b"Attack at dawn";
+
+    for g in [MaliciousG::One, MaliciousG::P, MaliciousG::PMinusOne] {
+        let mut attacker = DhMaliciousG::new(g);
+        let transcript = run_negotiated_echo(&group, message, &mut attacker)?;
+        println!(
+            "g = {:?}, Bob got {:?}",
+            g,
+            String::from_utf8_lossy(&transcript.received)
+        );
+        for (direction, plaintext) in &attacker.intercepted {
+            println!(
+                "  Intercepted {:?}: {:?}",
+                direction,
+                String::from_utf8_lossy(plaintext)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn secure_remote_password() -> Result<()> {
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone());
+    server.register("alice@example.com", "correct horse battery staple");
+
+    for password in ["correct horse battery staple", "hunter2"] {
+        let client = SrpClient::new(params.clone(), "alice@example.com", password);
+        println!(
+            "Login with {:?}: {}",
+            password,
+            if login(&client, &server)? {
+                "OK"
+            } else {
+                "denied"
+            }
+        );
+    }
+    Ok(())
+}
+
+fn srp_zero_key() -> Result<()> {
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone()).with_public_key_check(false);
+    server.register("alice@example.com", "correct horse battery staple");
+    let server = &server;
+
+    for multiple in 0..3 {
+        let (client_end, server_end) = Channel::pair();
+        let logged_in = std::thread::scope(|scope| {
+            scope.spawn(move || server.serve(&server_end));
+            srp_zero_key_login(&client_end, &params, "alice@example.com", multiple)
+        })?;
+        println!("Login with A = {multiple}·N: {logged_in}");
+    }
+    Ok(())
+}
+
+fn simple_srp_dictionary(wordlist: &Path) -> Result<()> {
+    let words = std::fs::read_to_string(wordlist)?
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+    ensure!(!words.is_empty(), "No words in {}", wordlist.display());
+    let password = &words[rand::thread_rng().gen_range(0, words.len())];
+
+    let params = SrpParams::nist();
+    let client = &SimpleSrpClient::new(params.clone(), "alice@example.com", password);
+    let (client_end, server_end) = Channel::pair();
+    let captured = std::thread::scope(|scope| {
+        scope.spawn(move || client.login(&client_end));
+        simple_srp_mitm(&params, &server_end)
+    })?;
+
+    let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+    match crack_simple_srp(&params, &captured, &words, threads) {
+        Some(found) => println!("Password for {} is {:?}", captured.identity, found),
+        None => bail!("Password not in {}", wordlist.display()),
+    }
+    Ok(())
+}
+
+fn textbook_rsa() -> Result<()> {
+    match invmod(&BigUint::from(17_u32), &BigUint::from(3120_u32)) {
+        Some(inverse) => println!("invmod(17, 3120) = {inverse}"),
+        None => bail!("17 should be invertible mod 3120"),
+    }
+
+    let keypair = RsaKeyPair::generate(1024, 3)?;
+    let message = b"Attack at dawn";
+    let ciphertext = keypair.public.encrypt(&BigUint::from_bytes_be(message))?;
+    let plaintext = keypair.private.decrypt(&ciphertext)?.to_bytes_be();
+    println!("Round trip: {:?}", String::from_utf8_lossy(&plaintext));
+    Ok(())
+}
+
+pub fn run(args: &CryptopalArgs) -> Result<()> {
+    match args.challenge {
+        33 => diffie_hellman()?,
+        34 => dh_key_fixing()?,
+        35 => dh_malicious_g()?,
+        36 => secure_remote_password()?,
+        37 => srp_zero_key()?,
+        38 => simple_srp_dictionary(
+            args.inputfile
+                .as_deref()
+                .unwrap_or(Path::new("inputs/c38.txt")),
+        )?,
+        39 => textbook_rsa()?,
+        n => bail!("Challenge {n} doesn't exist in set 5"),
+    };
+
+    Ok(())
+}
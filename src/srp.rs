@@ -0,0 +1,439 @@
+use crate::dh::{modexp, NIST_G, NIST_P};
+use crate::gen_rand_key;
+use crate::hash::sha256::Sha256;
+use crate::hash::MerkleDamgard;
+use crate::hmac::hmac;
+use anyhow::{anyhow, bail, ensure, Result};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+const SALT_SIZE: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SrpParams {
+    pub n: BigUint,
+    pub g: BigUint,
+    pub k: BigUint,
+}
+
+impl SrpParams {
+    /// SRP-6a over `n` and `g`, with the multiplier k = H(N | PAD(g)).
+    pub fn new(n: BigUint, g: BigUint) -> Self {
+        let k = BigUint::from_bytes_be(&Sha256::digest(&[n.to_bytes_be(), pad(&n, &g)].concat()));
+        SrpParams { n, g, k }
+    }
+
+    pub fn nist() -> Self {
+        Self::new(NIST_P.clone(), NIST_G.clone())
+    }
+
+    /// The scrambler u = H(PAD(A) | PAD(B)).
+    pub fn scrambler(&self, client_public: &BigUint, server_public: &BigUint) -> BigUint {
+        let data = [pad(&self.n, client_public), pad(&self.n, server_public)].concat();
+        BigUint::from_bytes_be(&Sha256::digest(&data))
+    }
+
+    /// The private key x = H(salt | H(I ":" P)).
+    pub fn private_key(&self, salt: &[u8], identity: &str, password: &str) -> BigUint {
+        let credentials = Sha256::digest(format!("{identity}:{password}").as_bytes());
+        BigUint::from_bytes_be(&Sha256::digest(&[salt, &credentials].concat()))
+    }
+
+    pub fn verifier(&self, salt: &[u8], identity: &str, password: &str) -> BigUint {
+        modexp(
+            &self.g,
+            &self.private_key(salt, identity, password),
+            &self.n,
+        )
+    }
+
+    /// Simplified SRP's private key x = H(salt | password).
+    pub fn simple_private_key(&self, salt: &[u8], password: &str) -> BigUint {
+        BigUint::from_bytes_be(&Sha256::digest(&[salt, password.as_bytes()].concat()))
+    }
+}
+
+fn pad(n: &BigUint, value: &BigUint) -> Vec<u8> {
+    let mut bytes = value.to_bytes_be();
+    let len = n.to_bytes_be().len();
+    if bytes.len() < len {
+        bytes.splice(0..0, std::iter::repeat_n(0, len - bytes.len()));
+    }
+    bytes
+}
+
+/// The session key K = H(S) and the proof HMAC(K, salt) a client sends to
+/// show it derived the same S as the server.
+pub fn session_proof(secret: &BigUint, salt: &[u8]) -> Vec<u8> {
+    let key = Sha256::digest(&secret.to_bytes_be());
+    hmac::<Sha256>(&key, salt)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SrpMessage {
+    Hello {
+        identity: String,
+        public: BigUint,
+    },
+    Challenge {
+        salt: Vec<u8>,
+        public: BigUint,
+    },
+    SimpleChallenge {
+        salt: Vec<u8>,
+        public: BigUint,
+        u: BigUint,
+    },
+    Proof(Vec<u8>),
+    Verdict(bool),
+}
+
+/// One end of an in-memory, bidirectional link.
+pub struct Channel {
+    tx: Sender<SrpMessage>,
+    rx: Receiver<SrpMessage>,
+}
+
+impl Channel {
+    pub fn pair() -> (Channel, Channel) {
+        let (client_tx, server_rx) = channel();
+        let (server_tx, client_rx) = channel();
+        (
+            Channel {
+                tx: client_tx,
+                rx: client_rx,
+            },
+            Channel {
+                tx: server_tx,
+                rx: server_rx,
+            },
+        )
+    }
+
+    pub fn send(&self, message: SrpMessage) -> Result<()> {
+        Ok(self.tx.send(message)?)
+    }
+
+    pub fn recv(&self) -> Result<SrpMessage> {
+        Ok(self.rx.recv()?)
+    }
+}
+
+struct Record {
+    salt: Vec<u8>,
+    verifier: BigUint,
+}
+
+pub struct SrpServer {
+    params: SrpParams,
+    users: HashMap<String, Record>,
+    check_public_key: bool,
+}
+
+impl SrpServer {
+    pub fn new(params: SrpParams) -> Self {
+        SrpServer {
+            params,
+            users: HashMap::new(),
+            check_public_key: true,
+        }
+    }
+
+    /// Turning this off gives the challenge 37 server, which accepts a
+    /// client public key of 0 mod N.
+    pub fn with_public_key_check(mut self, check: bool) -> Self {
+        self.check_public_key = check;
+        self
+    }
+
+    pub fn register(&mut self, identity: &str, password: &str) {
+        let salt = gen_rand_key(SALT_SIZE);
+        let verifier = self.params.verifier(&salt, identity, password);
+        self.users
+            .insert(identity.to_string(), Record { salt, verifier });
+    }
+
+    pub fn session(&self) -> ServerSession<'_> {
+        ServerSession {
+            server: self,
+            state: ServerState::AwaitingHello,
+        }
+    }
+
+    /// Runs a single login over `channel` and reports whether it succeeded.
+    pub fn serve(&self, channel: &Channel) -> Result<bool> {
+        let mut session = self.session();
+        loop {
+            channel.send(session.step(channel.recv()?)?)?;
+            if let Some(verdict) = session.verdict() {
+                return Ok(verdict);
+            }
+        }
+    }
+}
+
+enum ServerState {
+    AwaitingHello,
+    AwaitingProof { expected: Vec<u8> },
+    Done(bool),
+}
+
+pub struct ServerSession<'a> {
+    server: &'a SrpServer,
+    state: ServerState,
+}
+
+impl ServerSession<'_> {
+    pub fn step(&mut self, message: SrpMessage) -> Result<SrpMessage> {
+        match (&self.state, message) {
+            (ServerState::AwaitingHello, SrpMessage::Hello { identity, public }) => {
+                let SrpParams { n, g, k } = &self.server.params;
+                ensure!(
+                    !self.server.check_public_key || !(&public % n).is_zero(),
+                    "Client public key is a multiple of N"
+                );
+                let record = self
+                    .server
+                    .users
+                    .get(&identity)
+                    .ok_or_else(|| anyhow!("Unknown user {identity}"))?;
+
+                let private = rand::thread_rng().gen_biguint_range(&BigUint::one(), n);
+                let server_public = (k * &record.verifier + modexp(g, &private, n)) % n;
+                let u = self.server.params.scrambler(&public, &server_public);
+                let secret = modexp(&(public * modexp(&record.verifier, &u, n)), &private, n);
+
+                self.state = ServerState::AwaitingProof {
+                    expected: session_proof(&secret, &record.salt),
+                };
+                Ok(SrpMessage::Challenge {
+                    salt: record.salt.clone(),
+                    public: server_public,
+                })
+            }
+            (ServerState::AwaitingProof { expected }, SrpMessage::Proof(proof)) => {
+                let verdict = proof == *expected;
+                self.state = ServerState::Done(verdict);
+                Ok(SrpMessage::Verdict(verdict))
+            }
+            (_, other) => bail!("Server didn't expect {:?}", other),
+        }
+    }
+
+    pub fn verdict(&self) -> Option<bool> {
+        match self.state {
+            ServerState::Done(verdict) => Some(verdict),
+            _ => None,
+        }
+    }
+}
+
+pub struct SrpClient {
+    params: SrpParams,
+    identity: String,
+    password: String,
+}
+
+impl SrpClient {
+    pub fn new(params: SrpParams, identity: &str, password: &str) -> Self {
+        SrpClient {
+            params,
+            identity: identity.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    /// Starts a login, returning the session and the hello to send.
+    pub fn session(&self) -> (ClientSession<'_>, SrpMessage) {
+        let n = &self.params.n;
+        let private = rand::thread_rng().gen_biguint_range(&BigUint::one(), n);
+        let public = modexp(&self.params.g, &private, n);
+        let hello = SrpMessage::Hello {
+            identity: self.identity.clone(),
+            public: public.clone(),
+        };
+        let session = ClientSession {
+            client: self,
+            state: ClientState::AwaitingChallenge { private, public },
+        };
+        (session, hello)
+    }
+
+    pub fn login(&self, channel: &Channel) -> Result<bool> {
+        let (mut session, mut message) = self.session();
+        loop {
+            channel.send(message)?;
+            match session.step(channel.recv()?)? {
+                Some(next) => message = next,
+                None => {
+                    return session
+                        .verdict()
+                        .ok_or_else(|| anyhow!("Login didn't finish"))
+                }
+            }
+        }
+    }
+}
+
+enum ClientState {
+    AwaitingChallenge { private: BigUint, public: BigUint },
+    AwaitingVerdict,
+    Done(bool),
+}
+
+pub struct ClientSession<'a> {
+    client: &'a SrpClient,
+    state: ClientState,
+}
+
+impl ClientSession<'_> {
+    /// Handles a server message, returning the reply to send, if any.
+    pub fn step(&mut self, message: SrpMessage) -> Result<Option<SrpMessage>> {
+        match (&self.state, message) {
+            (
+                ClientState::AwaitingChallenge { private, public },
+                SrpMessage::Challenge {
+                    salt,
+                    public: server_public,
+                },
+            ) => {
+                let SrpParams { n, g, k } = &self.client.params;
+                ensure!(
+                    !(&server_public % n).is_zero(),
+                    "Server public key is a multiple of N"
+                );
+                let u = self.client.params.scrambler(public, &server_public);
+                let x = self.client.params.private_key(
+                    &salt,
+                    &self.client.identity,
+                    &self.client.password,
+                );
+
+                let base = (&server_public % n + n - k * modexp(g, &x, n) % n) % n;
+                let secret = modexp(&base, &(private + u * x), n);
+
+                self.state = ClientState::AwaitingVerdict;
+                Ok(Some(SrpMessage::Proof(session_proof(&secret, &salt))))
+            }
+            (ClientState::AwaitingVerdict, SrpMessage::Verdict(verdict)) => {
+                self.state = ClientState::Done(verdict);
+                Ok(None)
+            }
+            (_, other) => bail!("Client didn't expect {:?}", other),
+        }
+    }
+
+    pub fn verdict(&self) -> Option<bool> {
+        match self.state {
+            ClientState::Done(verdict) => Some(verdict),
+            _ => None,
+        }
+    }
+}
+
+/// Runs `client` against `server` with the server on its own thread.
+pub fn login(client: &SrpClient, server: &SrpServer) -> Result<bool> {
+    let (client_end, server_end) = Channel::pair();
+    std::thread::scope(|scope| {
+        let served = scope.spawn(move || server.serve(&server_end));
+        let logged_in = client.login(&client_end);
+        drop(client_end);
+        let served = served.join().map_err(|_| anyhow!("Server panicked"))?;
+        let (served, logged_in) = (served?, logged_in?);
+        ensure!(served == logged_in, "Client and server disagree");
+        Ok(served)
+    })
+}
+
+/// The server side of simplified SRP, where B = g^b doesn't depend on the
+/// verifier and u is a random 128-bit number sent along with it.
+pub struct SimpleSrpServer {
+    params: SrpParams,
+    users: HashMap<String, Record>,
+}
+
+impl SimpleSrpServer {
+    pub fn new(params: SrpParams) -> Self {
+        SimpleSrpServer {
+            params,
+            users: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, identity: &str, password: &str) {
+        let salt = gen_rand_key(SALT_SIZE);
+        let x = self.params.simple_private_key(&salt, password);
+        let verifier = modexp(&self.params.g, &x, &self.params.n);
+        self.users
+            .insert(identity.to_string(), Record { salt, verifier });
+    }
+
+    pub fn serve(&self, channel: &Channel) -> Result<bool> {
+        let (identity, public) = match channel.recv()? {
+            SrpMessage::Hello { identity, public } => (identity, public),
+            other => bail!("Server didn't expect {:?}", other),
+        };
+        let record = self
+            .users
+            .get(&identity)
+            .ok_or_else(|| anyhow!("Unknown user {identity}"))?;
+
+        let SrpParams { n, g, .. } = &self.params;
+        let mut rng = rand::thread_rng();
+        let private = rng.gen_biguint_range(&BigUint::one(), n);
+        let u = rng.gen_biguint(128);
+        channel.send(SrpMessage::SimpleChallenge {
+            salt: record.salt.clone(),
+            public: modexp(g, &private, n),
+            u: u.clone(),
+        })?;
+
+        let secret = modexp(&(public * modexp(&record.verifier, &u, n)), &private, n);
+        let verdict = match channel.recv()? {
+            SrpMessage::Proof(proof) => proof == session_proof(&secret, &record.salt),
+            other => bail!("Server didn't expect {:?}", other),
+        };
+        channel.send(SrpMessage::Verdict(verdict))?;
+        Ok(verdict)
+    }
+}
+
+pub struct SimpleSrpClient {
+    params: SrpParams,
+    identity: String,
+    password: String,
+}
+
+impl SimpleSrpClient {
+    pub fn new(params: SrpParams, identity: &str, password: &str) -> Self {
+        SimpleSrpClient {
+            params,
+            identity: identity.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    pub fn login(&self, channel: &Channel) -> Result<bool> {
+        let SrpParams { n, g, .. } = &self.params;
+        let private = rand::thread_rng().gen_biguint_range(&BigUint::one(), n);
+        channel.send(SrpMessage::Hello {
+            identity: self.identity.clone(),
+            public: modexp(g, &private, n),
+        })?;
+
+        let (salt, server_public, u) = match channel.recv()? {
+            SrpMessage::SimpleChallenge { salt, public, u } => (salt, public, u),
+            other => bail!("Client didn't expect {:?}", other),
+        };
+        let x = self.params.simple_private_key(&salt, &self.password);
+        let secret = modexp(&server_public, &(private + u * x), n);
+        channel.send(SrpMessage::Proof(session_proof(&secret, &salt)))?;
+
+        match channel.recv()? {
+            SrpMessage::Verdict(verdict) => Ok(verdict),
+            other => bail!("Client didn't expect {:?}", other),
+        }
+    }
+}
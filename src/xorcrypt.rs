@@ -15,6 +15,7 @@ pub trait XORCrypto {
     fn single_key_xor(&self, key: char) -> Vec<u8>;
     fn repeat_key_xor(&self, key: &Self) -> Vec<u8>;
     fn guess_xor_key(&self) -> Result<(char, f32)>;
+    fn guess_xor_byte<F: Fn(&[u8]) -> f32>(&self, score: F) -> Result<(u8, f32)>;
     fn guess_vigenere(&self) -> Result<Vec<u8>>;
 }
 
@@ -35,7 +36,7 @@ impl<T: ?Sized + AsRef<[u8]>> XORCrypto for T {
     fn single_key_xor(&self, key: char) -> Vec<u8> {
         self.as_ref()
             .iter()
-            .map(|&x| (x ^ key as u8))
+            .map(|&x| x ^ key as u8)
             .collect::<Vec<u8>>()
     }
 
@@ -49,22 +50,16 @@ impl<T: ?Sized + AsRef<[u8]>> XORCrypto for T {
     }
 
     fn guess_xor_key(&self) -> Result<(char, f32)> {
-        let mut guess = None;
-        let mut max_freq = 0.0;
-        for (i, freq) in PRINTABLE_ASCII
-            .iter()
-            .map(|&key| self.single_key_xor(key))
-            .map(|buf| buf.freq_rank())
-            .enumerate()
-        {
-            if freq > max_freq {
-                max_freq = freq;
-                guess = Some(i);
-            }
-        }
-        guess
-            .map(|i| (PRINTABLE_ASCII[i], max_freq))
-            .ok_or_else(|| anyhow::anyhow!("Can't guess XOR key for message"))
+        best_single_key(self.as_ref(), PRINTABLE_ASCII.iter().copied(), |buf| {
+            buf.freq_rank()
+        })
+    }
+
+    /// Like `guess_xor_key`, but tries every byte value and ranks the
+    /// candidate plaintexts with `score` instead of `freq_rank`.
+    fn guess_xor_byte<F: Fn(&[u8]) -> f32>(&self, score: F) -> Result<(u8, f32)> {
+        best_single_key(self.as_ref(), (0..=u8::MAX).map(char::from), score)
+            .map(|(key, freq)| (key as u8, freq))
     }
 
     fn guess_vigenere(&self) -> Result<Vec<u8>> {
@@ -91,7 +86,7 @@ impl<T: ?Sized + AsRef<[u8]>> XORCrypto for T {
                 })
                 .collect::<Vec<_>>();
 
-        normalized_keysizes.sort_by(|a, b| (a.1).cmp(&b.1));
+        normalized_keysizes.sort_by_key(|a| a.1);
 
         let mut guessed_keys = normalized_keysizes
             .iter()
@@ -110,3 +105,21 @@ impl<T: ?Sized + AsRef<[u8]>> XORCrypto for T {
         Ok(guessed_keys[0].1.to_vec())
     }
 }
+
+fn best_single_key<I, F>(data: &[u8], keys: I, score: F) -> Result<(char, f32)>
+where
+    I: Iterator<Item = char>,
+    F: Fn(&[u8]) -> f32,
+{
+    let mut guess = None;
+    let mut max_freq = 0.0;
+    for (key, freq) in keys.map(|key| (key, score(&data.single_key_xor(key)))) {
+        if freq > max_freq {
+            max_freq = freq;
+            guess = Some(key);
+        }
+    }
+    guess
+        .map(|key| (key, max_freq))
+        .ok_or_else(|| anyhow::anyhow!("Can't guess XOR key for message"))
+}
@@ -1,8 +1,28 @@
 use anyhow::Result;
-use cryptopals::aes::{AesCbc128, AesCbc256, AesEcb128, AesEcb256, Cipher};
+use cryptopals::aes::{
+    AesCbc128, AesCbc256, AesCtr128, AesCtrNist128, AesCtrNist256, AesEcb128, AesEcb256, AesMode,
+    Cipher, Oracle,
+};
+use cryptopals::attacks::*;
 use cryptopals::buffer::*;
-use cryptopals::decode_b64_file;
+use cryptopals::clock::*;
+use cryptopals::dh::protocol::*;
+use cryptopals::dh::*;
+use cryptopals::hash::md4::{self, Md4};
+use cryptopals::hash::sha1::{self, Sha1};
+use cryptopals::hash::sha256::Sha256;
+use cryptopals::hash::{secret_prefix_mac, MerkleDamgard};
+use cryptopals::hmac::hmac;
+use cryptopals::kv;
+use cryptopals::mt19937::*;
+use cryptopals::rsa::*;
+use cryptopals::server::*;
+use cryptopals::services::*;
+use cryptopals::srp::*;
 use cryptopals::xorcrypt::*;
+use cryptopals::{decode_b64_file, decode_b64_lines, gen_rand_key, gen_rand_key_with};
+use num_bigint::BigUint;
+use std::time::Duration;
 
 #[test]
 pub fn test_hex2base64() -> Result<()> {
@@ -61,7 +81,7 @@ pub fn test_repeat_key_xor() -> Result<()> {
 pub fn test_hamming_distance() -> Result<()> {
     let str1 = "this is a test";
     let str2 = "wokka wokka!!!";
-    assert_eq!(str1.hamming_distance(&str2)?, 37);
+    assert_eq!(str1.hamming_distance(str2)?, 37);
     Ok(())
 }
 
@@ -154,3 +174,767 @@ fn test_aes_cbc_256() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_ecb_byte_at_a_time() -> Result<()> {
+    let secret = decode_b64_file("inputs/c12.txt")?;
+    let oracle = Oracle::builder()
+        .with_mode(AesMode::ECB)
+        .with_suffix(&secret)
+        .build();
+    let recovered = ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+    assert_eq!(recovered, secret);
+    Ok(())
+}
+
+#[test]
+fn test_ecb_byte_at_a_time_with_prefix() -> Result<()> {
+    let secret = decode_b64_file("inputs/c12.txt")?;
+    for prefix_len in [1, 15, 16, 17, 40] {
+        let oracle = Oracle::builder()
+            .with_mode(AesMode::ECB)
+            .with_prefix_len(prefix_len)
+            .with_suffix(&secret)
+            .build();
+        let recovered = ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+        assert_eq!(recovered, secret);
+    }
+
+    let oracle = Oracle::builder()
+        .with_mode(AesMode::ECB)
+        .with_prefix(b"prefix ending in XXXXX")
+        .with_suffix(b"YYYYYYYYYYYYYYYYYYYY and then the secret")
+        .build();
+    assert_eq!(
+        locate_prefix(&|input: &[u8]| oracle.encrypt(input), 16)?,
+        22
+    );
+    Ok(())
+}
+
+#[test]
+fn test_kv() -> Result<()> {
+    let parsed = kv::parse("foo=bar&baz=qux&zap=zazzle")?;
+    assert_eq!(
+        parsed,
+        [("foo", "bar"), ("baz", "qux"), ("zap", "zazzle")]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+    );
+    assert_eq!(kv::encode(&parsed), "foo=bar&baz=qux&zap=zazzle");
+    assert_eq!(
+        kv::encode(&[("email", "foo@bar.com&role=admin")]),
+        "email=foo@bar.comroleadmin"
+    );
+    assert!(kv::parse("foo=bar&baz").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_ecb_cut_and_paste() -> Result<()> {
+    let service = ProfileService::new();
+    let forged = forge_admin_profile(|email| service.profile_for(email))?;
+    let profile = service.decrypt_profile(&forged)?;
+    assert!(profile.contains(&("role".to_owned(), "admin".to_owned())));
+    assert!(!profile.contains(&("role".to_owned(), "user".to_owned())));
+    Ok(())
+}
+
+fn check_admin_forgery<S, F>(service: &S, forge: F) -> Result<()>
+where
+    S: CommentService,
+    F: Fn(&dyn Fn(&str) -> Result<Vec<u8>>) -> Result<Vec<u8>>,
+{
+    let honest = service.encrypt_userdata(";admin=true;")?;
+    assert!(!service.is_admin(&honest)?);
+    let forged = forge(&|userdata| service.encrypt_userdata(userdata))?;
+    assert!(service.is_admin(&forged)?);
+    Ok(())
+}
+
+#[test]
+fn test_cbc_bitflipping() -> Result<()> {
+    check_admin_forgery(&CbcCommentService::new(), |oracle| {
+        forge_admin_comment_cbc(oracle)
+    })
+}
+
+#[test]
+fn test_pkcs7_validation() {
+    assert_eq!(
+        b"ICE ICE BABY\x04\x04\x04\x04".to_vec().try_strip(16),
+        Ok(b"ICE ICE BABY".to_vec())
+    );
+    assert_eq!(
+        b"ICE ICE BABY\x05\x05\x05\x05".to_vec().try_strip(16),
+        Err(PaddingError::Inconsistent(5))
+    );
+    assert_eq!(
+        b"ICE ICE BABY\x01\x02\x03\x04".to_vec().try_strip(16),
+        Err(PaddingError::Inconsistent(4))
+    );
+    assert_eq!(
+        b"ICE ICE BABY\x00".to_vec().try_strip(16),
+        Err(PaddingError::ZeroByte)
+    );
+    assert_eq!(
+        b"ICE\x11".to_vec().try_strip(16),
+        Err(PaddingError::Oversize(17))
+    );
+    assert_eq!(
+        b"\x02".to_vec().try_strip(16),
+        Err(PaddingError::Oversize(2))
+    );
+    assert_eq!(Vec::new().try_strip(16), Err(PaddingError::Empty));
+    assert_eq!(vec![16; 16].try_strip(16), Ok(Vec::new()));
+}
+
+#[test]
+fn test_padding_oracle() -> Result<()> {
+    let plaintexts = decode_b64_lines("inputs/c17.txt")?;
+    let server = PaddingOracleServer::new(plaintexts.clone());
+    for _ in 0..5 {
+        let (iv, ciphertext) = server.encrypt()?;
+        let mut blocks_seen = 0;
+        let recovered =
+            padding_oracle_attack::<AesCbc128, _, _>(&server, &iv, &ciphertext, |_, total, _| {
+                blocks_seen += 1;
+                assert_eq!(total, ciphertext.len() / 16);
+            })?;
+        assert!(plaintexts.contains(&recovered));
+        assert_eq!(blocks_seen, ciphertext.len() / 16);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_aes_ctr_128() -> Result<()> {
+    let ciphertext = "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ=="
+        .decode::<Base64>()?;
+    let key = b"YELLOW SUBMARINE";
+    let nonce = [0; 8];
+    let plaintext = AesCtr128::decrypt(key, Some(&nonce), &ciphertext)?;
+    assert_eq!(
+        plaintext,
+        b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ".to_vec()
+    );
+    assert_eq!(
+        AesCtr128::encrypt(key, Some(&nonce), &plaintext)?,
+        ciphertext
+    );
+    assert!(AesCtr128::encrypt(key, Some(&[0; 16]), &plaintext).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_aes_ctr_nist() -> Result<()> {
+    let counter = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".decode::<Hex>()?;
+    let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                     30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+        .decode::<Hex>()?;
+
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".decode::<Hex>()?;
+    let ciphertext = AesCtrNist128::encrypt(&key, Some(&counter), &plaintext)?;
+    assert_eq!(
+        ciphertext.encode::<Hex>(),
+        "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+         5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+    );
+    assert_eq!(
+        AesCtrNist128::decrypt(&key, Some(&counter), &ciphertext)?,
+        plaintext
+    );
+
+    let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4".decode::<Hex>()?;
+    let ciphertext = AesCtrNist256::encrypt(&key, Some(&counter), &plaintext)?;
+    assert_eq!(
+        ciphertext.encode::<Hex>(),
+        "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+         2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fixed_nonce_ctr() -> Result<()> {
+    let lines = decode_b64_lines("inputs/c19.txt")?;
+    let key = gen_rand_key(16);
+    let nonce = [0; 8];
+    let ciphertexts = lines
+        .iter()
+        .map(|line| AesCtr128::encrypt(&key, Some(&nonce), line))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Frequency scoring can't always tell a capital from its lowercase
+    // letter in the first column, so one wrong keystream byte is allowed.
+    let keystream = break_fixed_nonce_ctr(&ciphertexts)?;
+    let min_len = lines.iter().map(|line| line.len()).min().unwrap();
+    let actual = AesCtr128::keystream(&key, &nonce, 0, min_len)?;
+    assert_eq!(keystream.len(), min_len);
+    let wrong = keystream
+        .iter()
+        .zip(&actual)
+        .filter(|(k, a)| k != a)
+        .count();
+    assert!(wrong <= 1, "{wrong} keystream bytes are wrong");
+
+    let guessed = break_fixed_nonce_ctr_untruncated(&ciphertexts)?;
+    let max_len = lines.iter().map(|line| line.len()).max().unwrap();
+    let actual = AesCtr128::keystream(&key, &nonce, 0, max_len)?;
+    assert_eq!(guessed.len(), max_len);
+    let wrong = guessed
+        .iter()
+        .zip(&actual)
+        .filter(|((key, confidence), actual)| *confidence >= 0.5 && key != *actual)
+        .count();
+    assert!(wrong <= 1, "{wrong} confident keystream bytes are wrong");
+    assert!(guessed.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    Ok(())
+}
+
+#[test]
+fn test_mt19937() {
+    let mut mt = Mt19937::default();
+    let first = (0..5).map(|_| mt.next_u32()).collect::<Vec<_>>();
+    assert_eq!(
+        first,
+        [3499211612, 581869302, 3890346734, 3586334585, 545404204]
+    );
+    (5..9999).for_each(|_| {
+        mt.next_u32();
+    });
+    assert_eq!(mt.next_u32(), 4123659995);
+
+    let mut mt = Mt19937::new(1);
+    mt.seed(DEFAULT_SEED);
+    assert_eq!(mt.next_u32(), 3499211612);
+
+    let mut mt = Mt19937_64::default();
+    assert_eq!(mt.next_u64(), 14514284786278117030);
+    (1..9999).for_each(|_| {
+        mt.next_u64();
+    });
+    assert_eq!(mt.next_u64(), 9981545732273789042);
+
+    let mut rng = Mt19937::new(DEFAULT_SEED);
+    let key = gen_rand_key_with(&mut rng, 6);
+    assert_eq!(key, [0x5c, 0xbb, 0x91, 0xd0, 0xf6, 0x9e]);
+}
+
+#[test]
+fn test_crack_mt19937_seed() {
+    let clock = FakeClock::new(1_600_000_000);
+    let output = timestamp_seeded_output(&clock);
+    let now = clock.now() as u32;
+    assert!(now >= 1_600_000_080);
+
+    let seed = crack_mt19937_seed(output, now - 2000..=now).expect("Seed not found");
+    assert!((1_600_000_040..=now - 40).contains(&seed));
+    assert_eq!(Mt19937::new(seed).next_u32(), output);
+    assert_eq!(crack_mt19937_seed(output, now - 10..=now), None);
+}
+
+#[test]
+fn test_mt19937_clone() -> Result<()> {
+    for y in [0, 1, 0xdead_beef, u32::MAX, 3499211612] {
+        assert_eq!(untemper(temper(y)), y);
+    }
+
+    let mut mt = Mt19937::new(rand::random());
+    let mut outputs = [0; 624];
+    outputs.iter_mut().for_each(|out| *out = mt.next_u32());
+    let mut clone = Mt19937::clone_from_outputs(&outputs);
+    for _ in 0..1000 {
+        assert_eq!(clone.next_u32(), mt.next_u32());
+    }
+
+    let mut mt = Mt19937::new(rand::random());
+    let observed = gen_rand_key_with(&mut mt, 3001);
+    let mut clone = Mt19937::clone_from_bytes(&observed)?;
+    assert_eq!(
+        gen_rand_key_with(&mut clone, 64),
+        gen_rand_key_with(&mut mt, 64)
+    );
+    assert!(Mt19937::clone_from_bytes(&observed[..2000]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_mt19937_cipher() -> Result<()> {
+    let key = 0xbeef_u16.to_le_bytes();
+    let mut plaintext = gen_rand_key(23);
+    plaintext.extend(b"AAAAAAAAAAAAAA");
+    let ciphertext = Mt19937Cipher::encrypt(&key, None, &plaintext)?;
+    assert_eq!(Mt19937Cipher::decrypt(&key, None, &ciphertext)?, plaintext);
+    assert!(Mt19937Cipher::encrypt(&[0; 4], None, &plaintext).is_err());
+
+    assert_eq!(
+        crack_mt19937_cipher_seed(&ciphertext, b"AAAAAAAAAAAAAA"),
+        Some(0xbeef)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_password_reset_token() {
+    let clock = FakeClock::new(1_700_000_000);
+    let token = password_reset_token(&clock);
+    clock.sleep(30);
+    assert!(is_time_seeded_token(&token, &clock, 60));
+    assert!(!is_time_seeded_token(&token, &clock, 10));
+    assert!(!is_time_seeded_token(&gen_rand_key(16), &clock, 60));
+}
+
+#[test]
+fn test_aes_ctr_edit() -> Result<()> {
+    let key = b"YELLOW SUBMARINE";
+    let nonce = [7; 8];
+    let plaintext = b"The quick brown fox jumps over the lazy dog, twice over.".to_vec();
+    let ciphertext = AesCtr128::encrypt(key, Some(&nonce), &plaintext)?;
+
+    for (offset, newtext) in [
+        (0, &b"A"[..]),
+        (15, b"cat"),
+        (17, b"slow red hen hops"),
+        (50, b"thrice and more"),
+        (plaintext.len(), b"!"),
+    ] {
+        let mut edited = ciphertext.clone();
+        AesCtr128::edit(&mut edited, key, &nonce, offset, newtext)?;
+        let mut expected = plaintext.clone();
+        expected.truncate(offset);
+        expected.extend(newtext);
+        expected.extend(plaintext.iter().skip(offset + newtext.len()));
+        assert_eq!(AesCtr128::decrypt(key, Some(&nonce), &edited)?, expected);
+    }
+    let mut edited = ciphertext.clone();
+    assert!(AesCtr128::edit(&mut edited, key, &nonce, plaintext.len() + 1, b"!").is_err());
+    assert_eq!(edited, ciphertext);
+    Ok(())
+}
+
+#[test]
+fn test_ctr_edit_recover() -> Result<()> {
+    let plaintext = AesEcb128::decrypt(
+        b"YELLOW SUBMARINE",
+        None,
+        &decode_b64_file("inputs/c7.txt")?,
+    )?;
+    let mut storage = EditableStorage::new(&plaintext)?;
+    let ciphertext = storage.ciphertext().to_vec();
+    assert_ne!(ciphertext, plaintext);
+    let recovered = ctr_edit_recover(&ciphertext, |offset, newtext| {
+        storage.edit(offset, newtext)?;
+        Ok(storage.ciphertext().to_vec())
+    })?;
+    assert_eq!(recovered, plaintext);
+    assert_eq!(storage.ciphertext(), ciphertext.as_slice());
+    Ok(())
+}
+
+#[test]
+fn test_ctr_bitflipping() -> Result<()> {
+    check_admin_forgery(&CtrCommentService::new(), |oracle| {
+        forge_admin_comment_ctr(oracle)
+    })?;
+
+    let service = CtrCommentService::new();
+    let ciphertext = service.encrypt_userdata("XXXXXXXXXXXX")?;
+    let forged = forge_admin_comment_ctr(|userdata| service.encrypt_userdata(userdata))?;
+    let original = service.decrypt(&ciphertext)?;
+    let tampered = service.decrypt(&forged)?;
+    let changed = original
+        .iter()
+        .zip(&tampered)
+        .filter(|(a, b)| a != b)
+        .count();
+    assert_eq!(changed, b";admin=true;".len());
+    Ok(())
+}
+
+#[test]
+fn test_recover_key_as_iv() -> Result<()> {
+    let service = KeyAsIvService::new();
+    let ciphertext = service.encrypt_userdata("nothing to see here")?;
+    assert!(service.decrypt_ascii(&ciphertext).is_ok());
+
+    let key = recover_key_as_iv(&ciphertext, |ciphertext| service.decrypt_ascii(ciphertext))?;
+    assert_eq!(key, service.key());
+
+    let plaintext = AesCbc128::decrypt(&key, Some(&key), &ciphertext)?;
+    assert!(String::from_utf8(plaintext)?.contains("nothing to see here"));
+    Ok(())
+}
+
+#[test]
+fn test_sha1() {
+    for (input, expected) in [
+        (&b""[..], "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+        ),
+        (
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+            "a49b2446a02c645bf419f995b67091253a04a259",
+        ),
+    ] {
+        assert_eq!(Sha1::digest(input).encode::<Hex>(), expected);
+    }
+
+    let mut sha1 = Sha1::new();
+    for _ in 0..1000 {
+        sha1.update(&[b'a'; 1000]);
+    }
+    assert_eq!(
+        sha1.finalize().encode::<Hex>(),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+
+    let mut resumed = Sha1::from_state(sha1::INITIAL_STATE, 0);
+    resumed.update(b"abc");
+    assert_eq!(resumed.finalize(), Sha1::digest(b"abc"));
+
+    let mac = secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINE", b"message");
+    assert_eq!(mac, Sha1::digest(b"YELLOW SUBMARINEmessage"));
+    assert_ne!(
+        mac,
+        secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINF", b"message")
+    );
+}
+
+#[test]
+fn test_sha1_length_extension() -> Result<()> {
+    let service = MacService::<Sha1>::new();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let tag = service.sign(message);
+    assert!(service.verify(message, &tag));
+
+    let (forged, forged_tag) =
+        length_extension::<Sha1, _>(message, &tag, b";admin=true", 0..64, |message, tag| {
+            service.verify(message, tag)
+        })?;
+    assert!(forged.starts_with(message));
+    assert!(forged.ends_with(b";admin=true"));
+    assert!(service.verify(&forged, &forged_tag));
+    Ok(())
+}
+
+#[test]
+fn test_md4() {
+    for (input, expected) in [
+        (&b""[..], "31d6cfe0d16ae931b73c59d7e0c089c0"),
+        (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+        (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+        (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+        (
+            b"abcdefghijklmnopqrstuvwxyz",
+            "d79e1c308aa5bbcdeea8ed63df412da9",
+        ),
+        (
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+            "043f8582f241db351ce627e153e7f0e4",
+        ),
+        (
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+            "e33b4ddc9c38f2199c3e7b164fcc0536",
+        ),
+    ] {
+        assert_eq!(Md4::digest(input).encode::<Hex>(), expected);
+    }
+
+    let mut md4 = Md4::from_state(md4::INITIAL_STATE, 0);
+    md4.update(b"message ");
+    md4.update(b"digest");
+    assert_eq!(
+        md4.finalize().encode::<Hex>(),
+        "d9130a8164549fe818874806e1c7014b"
+    );
+}
+
+#[test]
+fn test_md4_length_extension() -> Result<()> {
+    let service = MacService::<Md4>::new();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let tag = service.sign(message);
+
+    let (forged, forged_tag) =
+        length_extension::<Md4, _>(message, &tag, b";admin=true", 0..64, |message, tag| {
+            service.verify(message, tag)
+        })?;
+    assert!(forged.ends_with(b";admin=true"));
+    assert!(service.verify(&forged, &forged_tag));
+    Ok(())
+}
+
+#[test]
+fn test_hmac_sha1() {
+    assert_eq!(
+        hmac::<Sha1>(&[0x0b; 20], b"Hi There").encode::<Hex>(),
+        "b617318655057264e28bc0b6fb378c8ef146be00"
+    );
+    assert_eq!(
+        hmac::<Sha1>(b"Jefe", b"what do ya want for nothing?").encode::<Hex>(),
+        "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+    );
+    assert_eq!(
+        hmac::<Sha1>(
+            &[0xaa; 80],
+            b"Test Using Larger Than Block-Size Key - Hash Key First"
+        )
+        .encode::<Hex>(),
+        "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+    );
+}
+
+#[test]
+fn test_hmac_timing_leak() -> Result<()> {
+    let key = gen_rand_key(16);
+    let server = HmacServer::start(&key, Duration::from_millis(2))?;
+    let expected = hmac::<Sha1>(&key, b"foo");
+    assert!(check_signature(server.addr(), "foo", &expected)?);
+    assert!(!check_signature(server.addr(), "foo", &[0; 20])?);
+
+    let prefix = timing_attack(2, 3, |signature| {
+        check_signature(server.addr(), "foo", signature)
+    })?;
+    assert_eq!(prefix, expected[..2]);
+    Ok(())
+}
+
+#[test]
+fn test_modexp() {
+    let small = |n: u32| BigUint::from(n);
+    assert_eq!(modexp(&small(4), &small(13), &small(497)), small(445));
+    assert_eq!(modexp(&small(5), &small(0), &small(37)), small(1));
+    assert_eq!(modexp(&small(5), &small(3), &small(1)), small(0));
+
+    let base = BigUint::from_bytes_be(&gen_rand_key(256));
+    let exponent = BigUint::from_bytes_be(&gen_rand_key(192));
+    assert_eq!(
+        modexp(&base, &exponent, &NIST_P),
+        base.modpow(&exponent, &NIST_P)
+    );
+}
+
+#[test]
+fn test_diffie_hellman() -> Result<()> {
+    for group in [
+        DhGroup::new(BigUint::from(37_u32), BigUint::from(5_u32))?,
+        DhGroup::nist(),
+    ] {
+        let (a, b) = (group.generate_keypair(), group.generate_keypair());
+        let s_a = group.shared_secret(&a, &b.public);
+        let s_b = group.shared_secret(&b, &a.public);
+        assert_eq!(s_a, s_b);
+        assert_eq!(derive_key(&s_a), derive_key(&s_b));
+        assert_eq!(derive_key(&s_a).len(), 16);
+    }
+    assert_eq!(NIST_P.bits(), 1536);
+
+    for p in [0_u32, 1] {
+        assert!(DhGroup::new(BigUint::from(p), BigUint::from(2_u32)).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dh_key_fixing() -> Result<()> {
+    let group = DhGroup::nist();
+    let message = b"Ice Ice Baby";
+
+    let transcript = run_echo(&group, message, &mut Relay)?;
+    assert_eq!(transcript.received, message);
+    assert_eq!(transcript.echoed, message);
+
+    let mut attacker = DhKeyFixing::default();
+    let transcript = run_echo(&group, message, &mut attacker)?;
+    assert_eq!(transcript.echoed, message);
+    assert_eq!(
+        attacker.intercepted,
+        vec![
+            (Direction::AliceToBob, message.to_vec()),
+            (Direction::BobToAlice, message.to_vec()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dh_malicious_g() -> Result<()> {
+    let group = DhGroup::nist();
+    let message = b"Ice Ice Baby";
+
+    let transcript = run_negotiated_echo(&group, message, &mut Relay)?;
+    assert_eq!(transcript.echoed, message);
+
+    for g in [MaliciousG::One, MaliciousG::P, MaliciousG::PMinusOne] {
+        // p - 1 lands on each of its two secrets with some probability, so
+        // give it a few runs to exercise both.
+        for _ in 0..4 {
+            let mut attacker = DhMaliciousG::new(g);
+            let transcript = run_negotiated_echo(&group, message, &mut attacker)?;
+            assert_eq!(transcript.received, message);
+            assert_eq!(transcript.echoed, message);
+            assert_eq!(
+                attacker.intercepted,
+                vec![
+                    (Direction::AliceToBob, message.to_vec()),
+                    (Direction::BobToAlice, message.to_vec()),
+                ]
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sha256() {
+    for (input, expected) in [
+        (
+            &b""[..],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ] {
+        assert_eq!(Sha256::digest(input).encode::<Hex>(), expected);
+    }
+    assert_eq!(
+        hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?").encode::<Hex>(),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn test_srp() -> Result<()> {
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone());
+    server.register("alice@example.com", "correct horse battery staple");
+
+    let client = SrpClient::new(
+        params.clone(),
+        "alice@example.com",
+        "correct horse battery staple",
+    );
+    assert!(login(&client, &server)?);
+
+    let client = SrpClient::new(params.clone(), "alice@example.com", "hunter2");
+    assert!(!login(&client, &server)?);
+
+    let client = SrpClient::new(params, "mallory@example.com", "hunter2");
+    assert!(login(&client, &server).is_err());
+
+    let mut session = server.session();
+    let hello = SrpMessage::Hello {
+        identity: "alice@example.com".to_string(),
+        public: BigUint::from(0_u32),
+    };
+    assert!(session.step(hello).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_srp_zero_key() -> Result<()> {
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone()).with_public_key_check(false);
+    server.register("alice@example.com", "correct horse battery staple");
+    let server = &server;
+
+    for multiple in 0..3 {
+        let (client_end, server_end) = Channel::pair();
+        let (served, logged_in) = std::thread::scope(|scope| {
+            let served = scope.spawn(move || server.serve(&server_end));
+            let logged_in = srp_zero_key_login(&client_end, &params, "alice@example.com", multiple);
+            (served.join().unwrap(), logged_in)
+        });
+        assert!(served?);
+        assert!(logged_in?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_simple_srp_dictionary() -> Result<()> {
+    let params = SrpParams::nist();
+    let mut server = SimpleSrpServer::new(params.clone());
+    server.register("alice@example.com", "sunshine");
+
+    let client = &SimpleSrpClient::new(params.clone(), "alice@example.com", "sunshine");
+    let (client_end, server_end) = Channel::pair();
+    let served = std::thread::scope(|scope| {
+        scope.spawn(move || client.login(&client_end));
+        server.serve(&server_end)
+    });
+    assert!(served?);
+
+    let (client_end, server_end) = Channel::pair();
+    let captured = std::thread::scope(|scope| {
+        scope.spawn(move || client.login(&client_end));
+        simple_srp_mitm(&params, &server_end)
+    })?;
+    assert_eq!(captured.identity, "alice@example.com");
+
+    let words = std::fs::read_to_string("inputs/c38.txt")?
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let found = crack_simple_srp(&params, &captured, &words, 4);
+    assert_eq!(found.as_deref(), Some("sunshine"));
+
+    let words = vec!["hunter2".to_string(), "letmein".to_string()];
+    assert_eq!(crack_simple_srp(&params, &captured, &words, 4), None);
+    Ok(())
+}
+
+#[test]
+fn test_invmod() {
+    let small = |n: u32| BigUint::from(n);
+    assert_eq!(invmod(&small(17), &small(3120)), Some(small(2753)));
+    assert_eq!(invmod(&small(3), &small(7)), Some(small(5)));
+    assert_eq!(invmod(&small(6), &small(9)), None);
+    assert_eq!(invmod(&small(3), &small(0)), None);
+}
+
+#[test]
+fn test_primality() -> Result<()> {
+    let small = |n: u32| BigUint::from(n);
+    for prime in [2, 3, 97, 7919, 65537] {
+        assert!(is_probable_prime(&small(prime), 20));
+    }
+    for composite in [0, 1, 91, 561, 7917] {
+        assert!(!is_probable_prime(&small(composite), 20));
+    }
+    assert!(is_probable_prime(&NIST_P, 20));
+    assert!(!is_probable_prime(&(&*NIST_P * 3_u32), 20));
+    assert_eq!(gen_prime(128)?.bits(), 128);
+    assert_eq!(gen_prime(2)?, small(3));
+    assert!(gen_prime(1).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rsa() -> Result<()> {
+    for e in [3, 65537] {
+        let keypair = RsaKeyPair::generate(512, e)?;
+        assert_eq!(keypair.public.n.bits(), 512);
+        assert_eq!(keypair.public.e, BigUint::from(e));
+
+        for _ in 0..4 {
+            let message = BigUint::from_bytes_be(&gen_rand_key(63));
+            let ciphertext = keypair.public.encrypt(&message)?;
+            assert_eq!(keypair.private.decrypt(&ciphertext)?, message);
+        }
+        assert!(keypair.public.encrypt(&keypair.public.n).is_err());
+    }
+    assert!(RsaKeyPair::generate(32, 3).is_err());
+    assert!(RsaKeyPair::generate(513, 3).is_err());
+    assert!(RsaKeyPair::generate(512, 4).is_err());
+    Ok(())
+}